@@ -0,0 +1,32 @@
+use reqwest::header;
+use serde_json::json;
+use tokio_compat_02::FutureExt;
+
+use crate::config::SophonConfig;
+
+/// Tells the operators something is wrong. Always goes to stderr, and to the
+/// configured webhook when there is one. Never fails, there is nobody left to tell.
+pub async fn alert(config: &SophonConfig, message: String) {
+    eprintln!("sophon alert: {}", message);
+
+    if let Some(webhook) = &config.alert_webhook {
+        if let Err(err) = post(webhook.as_str(), message).await {
+            eprintln!("sophon: alert webhook failed: {:?}", err);
+        }
+    }
+}
+
+async fn post(webhook: &str, message: String) -> Result<(), reqwest::Error> {
+    let body = json!({ "text": message, "content": message });
+
+    reqwest::Client::new()
+        .post(webhook)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .compat()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use crate::digest::Snapshot;
+use crate::twitter::Post;
+
+/// deviations from normal that count as a spike or lull
+const THRESHOLD: f64 = 3.0;
+/// past intervals needed before we trust the baseline
+const MIN_BASELINE: usize = 12;
+/// hours either side of the current hour of day that make up its baseline
+const HOUR_SPREAD: u64 = 1;
+/// snapshots further apart than this don't make an interval
+const MAX_INTERVAL: u64 = 60 * 60 * 2;
+/// seconds before the same series can be flagged again
+const COOLDOWN: u64 = 60 * 60 * 6;
+
+/// When each series was last flagged.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct AnomalyState {
+    pub flagged: HashMap<String, u64>,
+}
+
+/// A series of per hour rates read off consecutive snapshots.
+struct Series {
+    name: &'static str,
+    /// what it counts, ie `moves`
+    unit: &'static str,
+    value: fn(&Snapshot) -> u64,
+    /// when `value` was read, the tally is counted on its own schedule
+    at: fn(&Snapshot) -> u64,
+}
+
+const SERIES: &[Series] = &[
+    Series {
+        name: "arrivals",
+        unit: "moves",
+        value: |snapshot| snapshot.moves,
+        at: |snapshot| snapshot.counted_at,
+    },
+    Series {
+        name: "players",
+        unit: "new civilizations",
+        value: |snapshot| snapshot.players as u64,
+        at: |snapshot| snapshot.taken_at,
+    },
+    Series {
+        name: "planets",
+        unit: "planets initialized",
+        value: |snapshot| snapshot.planets.iter().sum(),
+        at: |snapshot| snapshot.taken_at,
+    },
+    Series {
+        name: "silver",
+        unit: "silver moved",
+        value: |snapshot| snapshot.silver_moved,
+        at: |snapshot| snapshot.counted_at,
+    },
+];
+
+/// Compares the latest interval of each series with the same time of day on
+/// earlier days, by z-score, and posts the ones far outside normal.
+pub fn detect_anomalies(state: &mut AnomalyState, snapshots: &VecDeque<Snapshot>) -> Vec<Post> {
+    let mut posts = vec![];
+
+    let intervals = snapshots
+        .iter()
+        .zip(snapshots.iter().skip(1))
+        .filter(|(from, to)| to.taken_at > from.taken_at)
+        .filter(|(from, to)| to.taken_at - from.taken_at <= MAX_INTERVAL)
+        .collect::<Vec<_>>();
+
+    let (from, to) = match intervals.last() {
+        Some(latest) => *latest,
+        None => return posts,
+    };
+    let now = to.taken_at;
+    let hour = hour_of_day(now);
+
+    for series in SERIES {
+        // over the time the values were read, not when the snapshots were
+        // taken, None when nothing was counted in between
+        let rate = |from: &Snapshot, to: &Snapshot| {
+            let (start, end) = ((series.at)(from), (series.at)(to));
+            if start == 0 || end <= start {
+                return None;
+            }
+            let change = (series.value)(to).saturating_sub((series.value)(from));
+            Some(change as f64 * 3600.0 / (end - start) as f64)
+        };
+
+        // same time of day, on earlier days
+        let baseline = intervals
+            .iter()
+            .filter(|(_, to)| now.saturating_sub(to.taken_at) > 60 * 60 * 12)
+            .filter(|(_, to)| {
+                let distance = (hour_of_day(to.taken_at) + 24 - hour) % 24;
+                distance <= HOUR_SPREAD || distance >= 24 - HOUR_SPREAD
+            })
+            .filter_map(|(from, to)| rate(from, to))
+            .collect::<Vec<_>>();
+        if baseline.len() < MIN_BASELINE {
+            continue;
+        }
+
+        let mean = baseline.iter().sum::<f64>() / baseline.len() as f64;
+        let variance =
+            baseline.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / baseline.len() as f64;
+        let deviation = variance.sqrt();
+        if deviation == 0.0 {
+            continue;
+        }
+
+        let current = match rate(from, to) {
+            Some(current) => current,
+            None => continue,
+        };
+        let z = (current - mean) / deviation;
+        if z.abs() < THRESHOLD {
+            continue;
+        }
+
+        if let Some(last) = state.flagged.get(series.name) {
+            if now.saturating_sub(*last) < COOLDOWN {
+                continue;
+            }
+        }
+        state.flagged.insert(series.name.to_string(), now);
+
+        let tweet = if z > 0.0 {
+            format!(
+                "Sophon 6e2a91c4 TX: Unusually high activity: {:.0} {} per hour, {:.1} deviations above normal for this time of day #darkforest",
+                current, series.unit, z
+            )
+        } else {
+            format!(
+                "Sophon 0b5d7e92 TX: Unusually quiet: {:.0} {} per hour against a usual {:.0} for this time of day #darkforest",
+                current, series.unit, mean
+            )
+        };
+        posts.push(Post::new(tweet, to.block));
+    }
+
+    posts
+}
+
+fn hour_of_day(at: u64) -> u64 {
+    (at / (60 * 60)) % 24
+}
@@ -0,0 +1,324 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use web3::types::{Address, U256};
+
+use crate::config::SophonConfig;
+use crate::cycle::{scan_every_cycle, Scan};
+use crate::graph::{planet_id, query_all, query_planet, Artifact, NO_OWNER};
+use crate::names::{planet_name, player_name};
+use crate::node::*;
+use crate::twitter::Post;
+use crate::{SophonError, SophonShare, SophonState};
+
+/// What the artifact tracker remembers between cycles.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ArtifactState {
+    /// last block scanned for artifact events
+    pub block: Option<u64>,
+    /// rarities someone has found at least one of
+    pub rarities_found: HashSet<String>,
+    /// legendary and mythic artifact ids to the last player known to hold them
+    pub holders: HashMap<String, String>,
+}
+
+/// Artifact rarity as the contract derives it from the level of the planet it
+/// was found on.
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+pub enum Rarity {
+    Common,
+    Rare,
+    Epic,
+    Legendary,
+    Mythic,
+}
+
+impl Rarity {
+    const ALL: [Rarity; 5] = [
+        Rarity::Common,
+        Rarity::Rare,
+        Rarity::Epic,
+        Rarity::Legendary,
+        Rarity::Mythic,
+    ];
+
+    /// lowest and highest level of planet this rarity is found on
+    fn levels(&self) -> (u32, u32) {
+        match self {
+            Rarity::Common => (0, 1),
+            Rarity::Rare => (2, 3),
+            Rarity::Epic => (4, 5),
+            Rarity::Legendary => (6, 7),
+            Rarity::Mythic => (8, u8::MAX as u32),
+        }
+    }
+
+    pub fn of(artifact: &DfArtifact) -> Self {
+        match artifact.planet_level {
+            0..=1 => Rarity::Common,
+            2..=3 => Rarity::Rare,
+            4..=5 => Rarity::Epic,
+            6..=7 => Rarity::Legendary,
+            _ => Rarity::Mythic,
+        }
+    }
+}
+
+impl std::fmt::Display for Rarity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// ie "Legendary Pyramid"
+pub fn describe_artifact(artifact: &DfArtifact) -> String {
+    let kind = match artifact.artifact_type {
+        1 => "Monolith",
+        2 => "Colossus",
+        3 => "Spaceship",
+        4 => "Pyramid",
+        _ => "artifact",
+    };
+    format!("{} {}", Rarity::of(artifact), kind)
+}
+
+/// Follows artifacts through the contract's FoundArtifact, DepositedArtifact and
+/// WithdrewArtifact events: posts the first artifact of each rarity, activations
+/// of epic and better artifacts, and legendary artifacts changing hands.
+pub async fn collect_artifacts(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
+    scan_every_cycle::<ArtifactState>(wrapped_state, rpc, config).await
+}
+
+impl Scan for ArtifactState {
+    fn of(state: &SophonState) -> Self {
+        state.artifacts.clone()
+    }
+
+    fn put(self, state: &mut SophonState) {
+        state.artifacts = self;
+    }
+
+    async fn scan(
+        &mut self,
+        _state: &SophonState,
+        rpc: &Rpc,
+        config: &SophonConfig,
+        block: u64,
+    ) -> Result<Vec<Post>, SophonError> {
+        // the first cycle only posts what happens from now on, and as we're
+        // starting mid-round, rarities found before we watched aren't firsts
+        // and legendaries found before then still change hands
+        let from = match self.block {
+            Some(last) => last + 1,
+            None => {
+                seed(self, rpc, config, block).await?;
+                block
+            }
+        };
+
+        let mut posts = vec![];
+        scan(self, rpc, config, from, block, &mut posts).await?;
+        self.block = Some(block);
+
+        Ok(posts)
+    }
+}
+
+/// Marks every rarity the subgraph already has an artifact of at `block` as
+/// found, and records who holds each legendary and mythic one.
+async fn seed(
+    state: &mut ArtifactState,
+    rpc: &Rpc,
+    config: &SophonConfig,
+    block: u64,
+) -> Result<(), SophonError> {
+    let round = config.round();
+
+    for rarity in Rarity::ALL.iter() {
+        let (min, max) = rarity.levels();
+        let found = query_all::<Artifact>(
+            round,
+            "planetLevel_gte: $min, planetLevel_lte: $max",
+            serde_json::json!({ "min": min, "max": max }),
+            block,
+            1,
+        )
+        .await?;
+
+        if !found.is_empty() {
+            state.rarities_found.insert(rarity.to_string());
+        }
+    }
+
+    let (legendary, _) = Rarity::Legendary.levels();
+    let legendaries = query_all::<Artifact>(
+        round,
+        "planetLevel_gte: $level",
+        serde_json::json!({ "level": legendary }),
+        block,
+        config.graph_max_results,
+    )
+    .await?;
+    let ids = legendaries
+        .iter()
+        .filter_map(Artifact::token_id)
+        .collect::<Vec<_>>();
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    for artifact in df_artifacts(rpc, ids, block).await? {
+        // a deposited artifact is held by whoever owns the planet it's on
+        let holder = if artifact.owner == df_address(rpc) {
+            query_planet(round, &planet_id(&artifact.location_id), block)
+                .await?
+                .map(|planet| planet.owner.id)
+                .filter(|owner| owner != NO_OWNER)
+        } else if artifact.owner != Address::zero() {
+            Some(format!("{:?}", artifact.owner))
+        } else {
+            None
+        };
+
+        if let Some(holder) = holder {
+            state.holders.insert(artifact.id.to_string(), holder);
+        }
+    }
+
+    Ok(())
+}
+
+/// Scans blocks `from..=to` for artifact events.
+async fn scan(
+    state: &mut ArtifactState,
+    rpc: &Rpc,
+    config: &SophonConfig,
+    from: u64,
+    to: u64,
+    posts: &mut Vec<Post>,
+) -> Result<(), NodeError> {
+    for event in df_events(rpc, "FoundArtifact", from, to).await? {
+        let (loc, player, id) = event_params(&event)?;
+        let artifact = df_artifact(rpc, id, to).await?;
+        let rarity = Rarity::of(&artifact);
+
+        if rarity >= Rarity::Legendary {
+            state
+                .holders
+                .insert(id.to_string(), format!("{:?}", player));
+        }
+
+        if state.rarities_found.insert(rarity.to_string()) {
+            let held = df_player_artifact_ids(rpc, player, to).await?.len();
+            let tweet = format!(
+                "Sophon 5e0c7d2a TX: first {} discovered at {} by {}, who now holds {} artifacts #darkforest",
+                describe_artifact(&artifact),
+                planet_name(config, &planet_id(&loc)),
+                player_name(config, &format!("{:?}", player)),
+                held,
+            );
+            posts.push(Post::new(tweet, event.block));
+        }
+    }
+
+    for event in df_events(rpc, "DepositedArtifact", from, to).await? {
+        let (loc, player, id) = event_params(&event)?;
+        let artifact = df_artifact(rpc, id, to).await?;
+
+        if Rarity::of(&artifact) >= Rarity::Epic {
+            let tweet = format!(
+                "Sophon 91f3b6e4 TX: {} activated a {} on {} #darkforest",
+                player_name(config, &format!("{:?}", player)),
+                describe_artifact(&artifact),
+                planet_name(config, &planet_id(&loc)),
+            );
+            posts.push(Post::new(tweet, event.block));
+        }
+    }
+
+    for event in df_events(rpc, "WithdrewArtifact", from, to).await? {
+        let (_, player, id) = event_params(&event)?;
+        let artifact = df_artifact(rpc, id, to).await?;
+
+        if Rarity::of(&artifact) >= Rarity::Legendary {
+            let player = format!("{:?}", player);
+            if let Some(post) = change_hands(state, config, &artifact, &player, event.block) {
+                posts.push(post);
+            }
+        }
+    }
+
+    // legendaries can also be traded wallet to wallet without the core contract
+    // seeing it, so check who owns each one now. Deposited ones are owned by the
+    // contract and keep their last holder
+    let ids = state
+        .holders
+        .keys()
+        .filter_map(|id| U256::from_dec_str(id).ok())
+        .collect::<Vec<_>>();
+    if !ids.is_empty() {
+        for artifact in df_artifacts(rpc, ids, to).await? {
+            if artifact.owner != df_address(rpc) && artifact.owner != Address::zero() {
+                let owner = format!("{:?}", artifact.owner);
+                if let Some(post) = change_hands(state, config, &artifact, &owner, to) {
+                    posts.push(post);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Records `player` as holding `artifact`, and a post if that's someone new.
+fn change_hands(
+    state: &mut ArtifactState,
+    config: &SophonConfig,
+    artifact: &DfArtifact,
+    player: &str,
+    block: u64,
+) -> Option<Post> {
+    let previous = state
+        .holders
+        .insert(artifact.id.to_string(), player.to_string());
+
+    match previous {
+        Some(previous) if previous != player => {
+            let tweet = format!(
+                "Sophon 2b8e60f1 TX: a {} has passed from {} to {} #darkforest",
+                describe_artifact(artifact),
+                player_name(config, &previous),
+                player_name(config, player),
+            );
+            Some(Post::new(tweet, block))
+        }
+        _ => None,
+    }
+}
+
+/// (loc, player, artifactId) of an artifact event
+fn event_params(event: &DfEvent) -> Result<(U256, Address, U256), NodeError> {
+    let param = |name: &str| {
+        event
+            .params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| param.value.clone())
+            .ok_or(NodeError::ContractResponseParse)
+    };
+
+    let loc = param("loc")?.into_uint();
+    let player = param("player")?.into_address();
+    let id = param("artifactId")?.into_uint();
+
+    match (loc, player, id) {
+        (Some(loc), Some(player), Some(id)) => Ok((loc, player, id)),
+        _ => Err(NodeError::ContractResponseParse),
+    }
+}
@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use web3::types::U256;
+
+use crate::artifacts::describe_artifact;
+use crate::config::SophonConfig;
+use crate::graph::*;
+use crate::names::{planet_name, player_name};
+use crate::node::*;
+use crate::profiles::now;
+use crate::relations::Moves;
+use crate::twitter::Post;
+use crate::SophonError;
+
+/// planets at or above this level are worth a capture post
+const CAPTURE_LEVEL: u32 = 4;
+/// seconds a home world stays watched after the last attack on it
+const CONTESTED_FOR: u64 = 60 * 60 * 24;
+/// planet ids looked up per query, they're written into the query itself
+const ID_CHUNK: usize = 500;
+/// artifacts read per bulkGetArtifactsByIds call while seeding
+const ARTIFACT_CHUNK: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct CaptureState {
+    /// last block scanned for PlayerInitialized, PlanetTransferred and artifact events
+    pub block: Option<u64>,
+    /// last owner seen of every planet we watch
+    pub owners: HashMap<String, String>,
+    /// home world planet id to the player who started there
+    pub home_worlds: HashMap<String, String>,
+    /// planets holding an artifact, whatever their level
+    pub holding: HashSet<String>,
+    /// home worlds and artifact holders have been read from the subgraph
+    pub seeded: bool,
+    /// home worlds under attack to when an attack on them was last seen
+    pub contested: HashMap<String, u64>,
+}
+
+/// Notices captures by diffing owners of high level planets, planets with hats
+/// or artifacts, and home worlds under attack between cycles. Planets given
+/// away by PlanetTransferred aren't captures. Runs in the relations cycle.
+pub async fn scan(
+    state: &mut CaptureState,
+    rpc: &Rpc,
+    config: &SophonConfig,
+    block: u64,
+    moves: &Moves,
+) -> Result<Vec<Post>, SophonError> {
+    let round = config.round();
+    let max = config.graph_max_results;
+    let now = now();
+
+    if !state.seeded {
+        seed(state, rpc, config, block).await?;
+    }
+
+    // the first cycle only looks at what happens from now on
+    let from = match state.block {
+        Some(last) => last + 1,
+        None => block,
+    };
+
+    for event in df_events(rpc, "PlayerInitialized", from, block).await? {
+        if let (Some(player), Some(loc)) = (param(&event, "player"), param(&event, "loc")) {
+            if let (Some(player), Some(loc)) = (player.into_address(), loc.into_uint()) {
+                state
+                    .home_worlds
+                    .insert(planet_id(&loc), format!("{:?}", player));
+            }
+        }
+    }
+
+    for event_name in ["FoundArtifact", "DepositedArtifact", "WithdrewArtifact"] {
+        for event in df_events(rpc, event_name, from, block).await? {
+            if let Some(loc) = param(&event, "loc").and_then(|loc| loc.into_uint()) {
+                if event_name == "WithdrewArtifact" {
+                    state.holding.remove(&planet_id(&loc));
+                } else {
+                    state.holding.insert(planet_id(&loc));
+                }
+            }
+        }
+    }
+
+    let mut transferred = HashSet::new();
+    for event in moves.transfers.iter().filter(|event| event.block >= from) {
+        if let Some(loc) = param(event, "loc").and_then(|loc| loc.into_uint()) {
+            transferred.insert(planet_id(&loc));
+        }
+    }
+    state.block = Some(block);
+
+    for arrival in &moves.arrivals {
+        let target = &arrival.toPlanet;
+        if state.home_worlds.contains_key(&target.id) && arrival.player.id != target.owner.id {
+            state.contested.insert(target.id.clone(), now);
+            state
+                .owners
+                .entry(target.id.clone())
+                .or_insert_with(|| target.owner.id.clone());
+        }
+    }
+    state
+        .contested
+        .retain(|_, attacked| now.saturating_sub(*attacked) < CONTESTED_FOR);
+
+    let mut planets = HashMap::new();
+    let high_level = query_all::<Planet>(
+        round,
+        "planetLevel_gte: $level",
+        serde_json::json!({ "level": CAPTURE_LEVEL }),
+        block,
+        max,
+    )
+    .await?;
+    let hatted = query_all::<Planet>(
+        round,
+        "hatLevel_gt: $level",
+        serde_json::json!({ "level": 0 }),
+        block,
+        max,
+    )
+    .await?;
+    for planet in high_level.into_iter().chain(hatted) {
+        planets.insert(planet.id.clone(), planet);
+    }
+
+    let watched = state
+        .holding
+        .iter()
+        .chain(state.contested.keys())
+        .filter(|id| !planets.contains_key(*id))
+        .cloned()
+        .collect::<Vec<_>>();
+    for ids in watched.chunks(ID_CHUNK) {
+        let filter = format!("id_in: {}", serde_json::json!(ids));
+        for planet in query_all::<Planet>(round, &filter, serde_json::json!({}), block, max).await?
+        {
+            planets.insert(planet.id.clone(), planet);
+        }
+    }
+
+    let owners = planets
+        .iter()
+        .map(|(id, planet)| (id.clone(), planet.owner.id.clone()))
+        .collect();
+
+    let mut posts = vec![];
+    for (id, previous) in changed_owners(state, &owners, &transferred) {
+        let planet = &planets[&id];
+        let owner = &planet.owner.id;
+
+        let mut spoils = vec![];
+        if state.home_worlds.get(&id) == Some(&previous) {
+            spoils.push("their home world".to_string());
+        }
+        if planet.hatLevel > 0 {
+            spoils.push(format!("a lvl {} hat", planet.hatLevel));
+        }
+        if let Ok(location) = U256::from_str_radix(&id, 16) {
+            if let Some(artifact) = df_planet_info(rpc, location, block).await?.held_artifact {
+                let artifact = df_artifact(rpc, artifact, block).await?;
+                spoils.push(format!("a {}", describe_artifact(&artifact)));
+            }
+        }
+
+        let spoils = if spoils.is_empty() {
+            String::new()
+        } else {
+            format!(" with {}", spoils.join(" and "))
+        };
+
+        let tweet = format!(
+            "Sophon 5c27e8d0 TX: {} captured {}, a {}{}, from {} #darkforest",
+            player_name(config, owner),
+            planet_name(config, &id),
+            planet.describe(),
+            spoils,
+            player_name(config, &previous),
+        );
+        posts.push(Post::new(tweet, block));
+    }
+
+    Ok(posts)
+}
+
+/// Home worlds from the subgraph's players, and the planets artifacts sit on
+/// from the contract, so captures of either are noticed from the first cycle.
+async fn seed(
+    state: &mut CaptureState,
+    rpc: &Rpc,
+    config: &SophonConfig,
+    block: u64,
+) -> Result<(), SophonError> {
+    let round = config.round();
+    let max = config.graph_max_results;
+
+    let players = query_all::<Player>(round, "", serde_json::json!({}), block, max).await?;
+    for player in players {
+        if let Some(home_world) = player.homeWorld {
+            state.home_worlds.insert(home_world.id, player.id);
+        }
+    }
+
+    let artifacts = query_all::<Artifact>(round, "", serde_json::json!({}), block, max).await?;
+    let ids = artifacts
+        .iter()
+        .filter_map(Artifact::token_id)
+        .collect::<Vec<_>>();
+    for ids in ids.chunks(ARTIFACT_CHUNK) {
+        for artifact in df_artifacts(rpc, ids.to_vec(), block).await? {
+            if !artifact.location_id.is_zero() {
+                state.holding.insert(planet_id(&artifact.location_id));
+            }
+        }
+    }
+
+    state.seeded = true;
+    Ok(())
+}
+
+/// Planets that changed hands since the last cycle other than by transfer,
+/// sorted by id, with who held them before.
+fn changed_owners(
+    state: &mut CaptureState,
+    owners: &BTreeMap<String, String>,
+    transferred: &HashSet<String>,
+) -> Vec<(String, String)> {
+    let mut changed = vec![];
+    for (id, owner) in owners {
+        let previous = match state.owners.insert(id.clone(), owner.clone()) {
+            Some(previous) => previous,
+            None => continue,
+        };
+        if previous == *owner || previous == NO_OWNER || transferred.contains(id) {
+            continue;
+        }
+        changed.push((id.clone(), previous));
+    }
+
+    changed
+}
+
+fn param(event: &DfEvent, name: &str) -> Option<ethabi::Token> {
+    event
+        .params
+        .iter()
+        .find(|param| param.name == name)
+        .map(|param| param.value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// subgraph id of a planet whose location starts with zero nibbles
+    const PLANET: &str = "0000012c4ba3f95e67d1a9b8c0f2e3d4a5b6c7d8e9f00112233445566778899a";
+
+    fn owners(owner: &str) -> BTreeMap<String, String> {
+        vec![(PLANET.to_string(), owner.to_string())]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn captures_planet_with_leading_zeros() {
+        let location = U256::from_str_radix(PLANET, 16).unwrap();
+        let mut state = CaptureState::default();
+        state
+            .home_worlds
+            .insert(planet_id(&location), "0xaaaa".to_string());
+
+        assert!(changed_owners(&mut state, &owners("0xaaaa"), &HashSet::new()).is_empty());
+
+        let changed = changed_owners(&mut state, &owners("0xbbbb"), &HashSet::new());
+        assert_eq!(changed, vec![(PLANET.to_string(), "0xaaaa".to_string())]);
+        assert_eq!(state.home_worlds.get(PLANET), Some(&"0xaaaa".to_string()));
+    }
+
+    #[test]
+    fn transfers_with_leading_zeros_arent_captures() {
+        let location = U256::from_str_radix(PLANET, 16).unwrap();
+        let mut state = CaptureState::default();
+        let transferred = vec![planet_id(&location)].into_iter().collect();
+
+        changed_owners(&mut state, &owners("0xaaaa"), &HashSet::new());
+        assert!(changed_owners(&mut state, &owners("0xbbbb"), &transferred).is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::alert::alert;
+use crate::config::SophonConfig;
+use crate::graph::{query_graph_meta, Block};
+use crate::node::{df_block_hash, df_head, Rpc};
+use crate::SophonState;
+
+/// Picks the block a collection cycle reads at: the subgraph's latest indexed
+/// block, as long as the subgraph is keeping up with chain head and is on the
+/// canonical chain. Returns None when posting should wait for a later cycle.
+pub async fn pin_block(state: &mut SophonState, rpc: &Rpc, config: &SophonConfig) -> Option<u64> {
+    let graph_meta = query_graph_meta(config.round()).await.ok()?;
+    let block = graph_meta.block.number as u64;
+
+    // the last block we posted from, is it still canonical
+    // graph-node may not know a block's hash, then there's nothing to compare
+    if let Some((last, last_hash)) = state
+        .last_block
+        .clone()
+        .and_then(|last| last.hash.clone().map(|hash| (last, hash)))
+    {
+        if let Ok(hash) = df_block_hash(rpc, last.number as u64).await {
+            if hash != last_hash.to_lowercase() {
+                // whatever was queued from the orphaned block on may not have happened
+                let reorged = last.number as u64;
+                let queued = state.tweets.len();
+                state
+                    .tweets
+                    .retain(|post| post.block.is_none_or(|block| block < reorged));
+
+                let message = format!(
+                    "reorg at block {}: posted from {} but the chain now has {}, dropped {} queued posts",
+                    last.number,
+                    last_hash,
+                    hash,
+                    queued - state.tweets.len()
+                );
+                alert(config, message).await;
+
+                state.last_block = Some(Block {
+                    number: last.number,
+                    hash: Some(hash),
+                });
+            }
+        }
+    }
+
+    // and is the subgraph itself on the canonical chain
+    if let (Some(graph_hash), Ok(hash)) = (&graph_meta.block.hash, df_block_hash(rpc, block).await)
+    {
+        if hash != graph_hash.to_lowercase() {
+            let message = format!(
+                "subgraph block {} is {} but the chain has {}, holding posts",
+                block, graph_hash, hash
+            );
+            alert(config, message).await;
+            return None;
+        }
+    }
+
+    if let Ok(head) = df_head(rpc).await {
+        let lag = head.saturating_sub(block);
+
+        if lag > config.max_graph_lag {
+            // only shout on the way in, not every cycle
+            if !state.graph_lagging {
+                let message = format!(
+                    "subgraph is {} blocks behind chain head {}, holding posts",
+                    lag, head
+                );
+                alert(config, message).await;
+                state.graph_lagging = true;
+            }
+            return None;
+        }
+
+        if state.graph_lagging {
+            let message = format!("subgraph caught up to within {} blocks, posting again", lag);
+            alert(config, message).await;
+            state.graph_lagging = false;
+        }
+    }
+
+    state.last_block = Some(graph_meta.block);
+
+    Some(block)
+}
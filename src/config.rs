@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+const CONFIG_FILE: &str = "sophon_config.json";
+
+/// Operator settings, read from `sophon_config.json` at startup. Every field is
+/// optional in the file, anything missing falls back to the defaults below.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SophonConfig {
+    /// json-rpc endpoints in order of preference, `ws://` and `wss://` urls use a websocket
+    pub rpc_endpoints: Vec<String>,
+    /// how many endpoints must agree on a value before a node milestone is posted
+    pub rpc_quorum: usize,
+    /// Multicall contract to batch contract reads through, plain json-rpc batches otherwise
+    pub multicall_address: Option<String>,
+    /// blocks the subgraph may trail chain head before we stop posting from it
+    pub max_graph_lag: u64,
+    /// most results fetched for any one subgraph entity per cycle
+    pub graph_max_results: usize,
+    /// url operator alerts are POSTed to as json `{"text", "content"}`, slack and discord both understand one of them
+    pub alert_webhook: Option<String>,
+    /// json object of player address to ENS name or twitter handle to post instead of the address
+    pub names_file: String,
+    /// json array of the Dark Forest client's planet name words, copied from
+    /// its source, so posts name planets the way players see them
+    pub planet_words_file: String,
+    /// name of the round to follow, one of `rounds`
+    pub round: String,
+    /// every round sophon knows how to follow
+    pub rounds: Vec<Round>,
+    /// planets and players to warn about large incoming attacks
+    pub watch: Watch,
+}
+
+/// Where one Dark Forest round lives and how its subgraph is shaped, so the
+/// same binary can follow whichever round is running.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Round {
+    /// what `round` refers to this round by, ie `v0.5`
+    pub name: String,
+    /// DarkForestCore address, hex with or without 0x
+    pub contract_address: String,
+    /// the round's contract abi, a bare abi array or a DarkForestCore.json style
+    /// artifact with an `abi` key. The built in v0.5 abi when unset
+    pub abi_path: Option<String>,
+    pub subgraph_url: String,
+    #[serde(default)]
+    pub mapping: EntityMapping,
+    /// contract events only this round has, posted as they happen
+    #[serde(default)]
+    pub events: Vec<RoundEvent>,
+    /// unix seconds token minting ends, which ends the round. The contract
+    /// only has a setter for it so it has to be configured, without it the
+    /// round's end is never noticed, announced or archived
+    #[serde(default)]
+    pub ends_at: Option<u64>,
+}
+
+/// The parts of a query that changed between subgraph schema versions.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EntityMapping {
+    /// `where` filter for arrivals still in flight
+    pub arrivals_in_flight: String,
+}
+
+/// Planets and players whose inbound energy we warn about ahead of arrival.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Watch {
+    /// planet location ids and player addresses
+    pub targets: Vec<String>,
+    /// smallest incoming energy worth a warning
+    pub min_energy: u32,
+    /// post warnings publicly, otherwise they only go to the alert webhook
+    pub post: bool,
+}
+
+/// A contract event to post about, ie a capture or space junk change.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoundEvent {
+    /// event name in the round's abi
+    pub event: String,
+    /// tweet text, `{param}` is replaced with the event's param of that name
+    pub template: String,
+}
+
+impl Default for EntityMapping {
+    fn default() -> Self {
+        EntityMapping {
+            arrivals_in_flight: "processedAt: null".to_string(),
+        }
+    }
+}
+
+impl Default for Watch {
+    fn default() -> Self {
+        Watch {
+            targets: vec![],
+            min_energy: 1_000_000,
+            post: false,
+        }
+    }
+}
+
+impl SophonConfig {
+    /// The round being followed, None if `round` doesn't name one of `rounds`.
+    pub fn current_round(&self) -> Option<&Round> {
+        self.rounds.iter().find(|round| round.name == self.round)
+    }
+
+    /// The round configured after the current one, followed once it ends.
+    pub fn next_round(&self) -> Option<&Round> {
+        let current = self
+            .rounds
+            .iter()
+            .position(|round| round.name == self.round)?;
+        self.rounds.get(current + 1)
+    }
+
+    /// The round being followed, main checks it exists before anything runs.
+    pub fn round(&self) -> &Round {
+        self.current_round().expect("unknown round")
+    }
+}
+
+impl Default for SophonConfig {
+    fn default() -> Self {
+        SophonConfig {
+            rpc_endpoints: vec!["https://rpc.xdaichain.com".to_string()],
+            rpc_quorum: 1,
+            multicall_address: None,
+            max_graph_lag: 120,
+            graph_max_results: 10_000,
+            alert_webhook: None,
+            names_file: "sophon_names.json".to_string(),
+            planet_words_file: "sophon_planet_words.json".to_string(),
+            round: "v0.5".to_string(),
+            rounds: vec![Round {
+                name: "v0.5".to_string(),
+                contract_address: "678ACb78948Be7F354B28DaAb79B1ABD81574c1B".to_string(),
+                abi_path: None,
+                subgraph_url:
+                    "https://api.thegraph.com/subgraphs/name/jacobrosenthal/dark-forest-v05"
+                        .to_string(),
+                mapping: EntityMapping::default(),
+                events: vec![],
+                ends_at: None,
+            }],
+            watch: Watch::default(),
+        }
+    }
+}
+
+/// A missing config file means defaults, a malformed one is an error so we
+/// don't quietly run against the wrong endpoints.
+pub fn load_config() -> Result<SophonConfig, serde_json::Error> {
+    match std::fs::read_to_string(CONFIG_FILE) {
+        Ok(config_json) => serde_json::from_str::<SophonConfig>(config_json.as_str()),
+        Err(_) => Ok(SophonConfig::default()),
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+use crate::chain::pin_block;
+use crate::config::SophonConfig;
+use crate::node::Rpc;
+use crate::twitter::Post;
+use crate::{save_state, SophonError, SophonShare, SophonState, COLLECT_DELAY};
+
+/// A collector that scans its own part of the state once a cycle.
+pub trait Scan: Sized {
+    /// a copy of this collector's part of `state`
+    fn of(state: &SophonState) -> Self;
+
+    /// puts a scanned copy back into `state`
+    fn put(self, state: &mut SophonState);
+
+    /// One cycle at `block`, returning what to post. It runs on a copy, so an
+    /// error leaves nothing half counted and the cycle is retried whole.
+    async fn scan(
+        &mut self,
+        state: &SophonState,
+        rpc: &Rpc,
+        config: &SophonConfig,
+        block: u64,
+    ) -> Result<Vec<Post>, SophonError>;
+
+    /// whether the collector has nothing left to do
+    fn finished(&self) -> bool {
+        false
+    }
+}
+
+/// Runs `S` every COLLECT_DELAY at a pinned block, queueing its posts and
+/// saving after each cycle that succeeds. Returns once `S` has finished.
+pub async fn scan_every_cycle<S: Scan>(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
+    loop {
+        let mut finished = false;
+
+        // scope for mutex release
+        {
+            let mut share = wrapped_state.lock().await;
+
+            if let Some(block) = pin_block(&mut share.state, &rpc, &config).await {
+                let mut next = S::of(&share.state);
+
+                if let Ok(posts) = next.scan(&share.state, &rpc, &config, block).await {
+                    finished = next.finished();
+                    next.put(&mut share.state);
+                    share.state.tweets.extend(posts);
+
+                    save_state(&share.state);
+                }
+            }
+        }
+
+        if finished {
+            return Ok(());
+        }
+
+        sleep(COLLECT_DELAY).await;
+    }
+}
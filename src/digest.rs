@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+use crate::anomaly::detect_anomalies;
+use crate::chain::pin_block;
+use crate::config::SophonConfig;
+use crate::graph::Arrival;
+use crate::names::player_name;
+use crate::node::*;
+use crate::profiles::now;
+use crate::records::Window;
+use crate::twitter::{send_thread, Post};
+use crate::{save_state, SophonError, SophonShare, COLLECT_DELAY};
+
+/// movers listed in a digest
+const TOP_MOVERS: usize = 5;
+
+/// Running totals since Sophon started counting, snapshots are diffed to get
+/// what happened in a window.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Tally {
+    /// newest arrival id counted
+    pub last_arrival_id: u32,
+    /// unix seconds the arrival counts were last brought up to date
+    pub counted_at: u64,
+    pub moves: u64,
+    pub silver_moved: u64,
+    pub hats: u64,
+    pub artifacts: u64,
+    /// moves per player since the last daily digest
+    pub movers_day: HashMap<String, u64>,
+    /// moves per player since the last weekly digest
+    pub movers_week: HashMap<String, u64>,
+}
+
+impl Tally {
+    /// Counts arrivals departed since the last call. Arrival ids count every
+    /// move, silver and movers only the ones still in flight when we looked.
+    pub fn count_arrivals(&mut self, arrivals: &[Arrival]) {
+        let newest = match arrivals.iter().map(|arrival| arrival.arrivalId).max() {
+            Some(newest) => newest,
+            None => return,
+        };
+
+        // the first look only sets where counting starts
+        if self.last_arrival_id > 0 && newest > self.last_arrival_id {
+            self.moves += (newest - self.last_arrival_id) as u64;
+
+            for arrival in arrivals {
+                if arrival.arrivalId > self.last_arrival_id {
+                    self.silver_moved += arrival.milliSilverMoved as u64 / 1000;
+                    *self
+                        .movers_day
+                        .entry(arrival.player.id.clone())
+                        .or_default() += 1;
+                    *self
+                        .movers_week
+                        .entry(arrival.player.id.clone())
+                        .or_default() += 1;
+                }
+            }
+        }
+
+        self.last_arrival_id = std::cmp::max(self.last_arrival_id, newest);
+        self.counted_at = now();
+    }
+
+    pub fn count_discoveries(&mut self, hats: usize, artifacts: usize) {
+        self.hats += hats as u64;
+        self.artifacts += artifacts as u64;
+    }
+}
+
+/// The universe as it stood at one block.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    /// unix seconds
+    pub taken_at: u64,
+    pub block: u64,
+    pub players: u32,
+    pub radius: u64,
+    /// initialized planets by level
+    pub planets: Vec<u64>,
+    /// when the tally below was counted, which lags `taken_at`
+    #[serde(default)]
+    pub counted_at: u64,
+    pub moves: u64,
+    pub silver_moved: u64,
+    pub hats: u64,
+    pub artifacts: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct DigestState {
+    pub tally: Tally,
+    /// a week and a day of snapshots, oldest first
+    pub snapshots: VecDeque<Snapshot>,
+    /// start of the last day and week digested
+    pub last_day: u64,
+    pub last_week: u64,
+}
+
+/// Snapshots the universe every cycle, and posts a thread summing up each day
+/// and week once it ends.
+pub async fn collect_digest(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
+    loop {
+        let mut threads = vec![];
+
+        // scope for mutex release
+        {
+            let mut share = wrapped_state.lock().await;
+
+            if let Some(block) = pin_block(&mut share.state, &rpc, &config).await {
+                let state = &mut share.state;
+                let digest = &mut state.digest;
+
+                if let Some(snapshot) = snapshot(&rpc, &digest.tally, block).await {
+                    let now = snapshot.taken_at;
+                    digest.snapshots.push_back(snapshot);
+                    while let Some(oldest) = digest.snapshots.front() {
+                        if now.saturating_sub(oldest.taken_at)
+                            <= Window::Week.length() + Window::Day.length()
+                        {
+                            break;
+                        }
+                        digest.snapshots.pop_front();
+                    }
+
+                    let anomalies = detect_anomalies(&mut state.anomalies, &digest.snapshots);
+                    state.tweets.extend(anomalies);
+
+                    for window in Window::ALL.iter() {
+                        let start = window.start(now);
+                        let last = match window {
+                            Window::Day => &mut digest.last_day,
+                            Window::Week => &mut digest.last_week,
+                        };
+                        if *last >= start {
+                            continue;
+                        }
+
+                        // the first window we see started before we were watching
+                        let first = *last == 0;
+                        *last = start;
+
+                        let movers = match window {
+                            Window::Day => &mut digest.tally.movers_day,
+                            Window::Week => &mut digest.tally.movers_week,
+                        };
+                        let movers = std::mem::take(movers);
+
+                        if !first {
+                            if let Some(thread) = summarize(&config, digest, *window, &movers) {
+                                threads.push(thread);
+                            }
+                        }
+                    }
+
+                    save_state(&share.state);
+                }
+            }
+        }
+
+        for thread in threads {
+            // the window has already moved on, so what didn't go out is queued
+            if let Err((unsent, err)) = send_thread(thread).await {
+                eprintln!("sophon: digest failed: {:?}", err);
+
+                let mut share = wrapped_state.lock().await;
+                for tweet in unsent {
+                    share.state.tweets.push_back(Post {
+                        text: tweet,
+                        block: None,
+                    });
+                }
+
+                save_state(&share.state);
+            }
+        }
+
+        sleep(COLLECT_DELAY).await;
+    }
+}
+
+async fn snapshot(rpc: &Rpc, tally: &Tally, block: u64) -> Option<Snapshot> {
+    Some(Snapshot {
+        taken_at: now(),
+        block,
+        players: df_players(rpc, block).await.ok()?,
+        radius: df_radius(rpc, block).await.ok()?,
+        planets: df_counts(rpc, block).await.ok()?,
+        counted_at: tally.counted_at,
+        moves: tally.moves,
+        silver_moved: tally.silver_moved,
+        hats: tally.hats,
+        artifacts: tally.artifacts,
+    })
+}
+
+/// The thread for the `window` that just ended, from the first snapshot in it
+/// to the latest. None without a snapshot from the start of the window.
+fn summarize(
+    config: &SophonConfig,
+    digest: &DigestState,
+    window: Window,
+    movers: &HashMap<String, u64>,
+) -> Option<Vec<String>> {
+    let to = digest.snapshots.back()?;
+    let start = window.start(to.taken_at) - window.length();
+    let from = digest
+        .snapshots
+        .iter()
+        .find(|snapshot| snapshot.taken_at >= start)?;
+    // we missed most of the window
+    if from.taken_at - start > window.length() / 4 {
+        return None;
+    }
+
+    let title = match window {
+        Window::Day => "Daily",
+        Window::Week => "Weekly",
+    };
+
+    let mut thread = vec![format!(
+        "{} new civilizations, {} moves carrying {} silver, and the universe grew from {} to {}",
+        to.players.saturating_sub(from.players),
+        to.moves - from.moves,
+        to.silver_moved - from.silver_moved,
+        from.radius,
+        to.radius,
+    )];
+
+    let planets = to
+        .planets
+        .iter()
+        .enumerate()
+        .map(|(level, count)| {
+            let before = from.planets.get(level).copied().unwrap_or_default();
+            (level, count.saturating_sub(before))
+        })
+        .filter(|(_, count)| *count > 0)
+        .map(|(level, count)| format!("lvl{}:+{}", level, count))
+        .collect::<Vec<_>>();
+    if !planets.is_empty() {
+        thread.push(format!("Planets initialized: {}", planets.join(", ")));
+    }
+
+    let mut top = movers.iter().collect::<Vec<_>>();
+    top.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let top = top
+        .iter()
+        .take(TOP_MOVERS)
+        .map(|(player, moves)| format!("{} ({})", player_name(config, player), moves))
+        .collect::<Vec<_>>();
+    if !top.is_empty() {
+        thread.push(format!("Top movers: {}", top.join(", ")));
+    }
+
+    thread.push(format!(
+        "{} hats and {} artifacts discovered",
+        to.hats - from.hats,
+        to.artifacts - from.artifacts,
+    ));
+
+    let total = thread.len();
+    let thread = thread
+        .into_iter()
+        .enumerate()
+        .map(|(i, text)| {
+            format!(
+                "Sophon 5a1f0e27 TX: {} digest ({}/{}): {} #darkforest",
+                title,
+                i + 1,
+                total,
+                text
+            )
+        })
+        .collect();
+
+    Some(thread)
+}
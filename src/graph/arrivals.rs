@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use super::planets::{Planet, PLANET_FIELDS};
+use super::players::{Player, PLAYER_FIELDS};
+use super::Entity;
+
+pub static ARRIVAL_FIELDS: &str = include_str!("arrivals.graphql");
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Arrival {
+    pub id: String,
+    pub arrivalId: u32,
+    pub arrivalTime: u32,
+    pub departureTime: u32,
+    pub receivedAt: u32,
+    pub milliEnergyArriving: u32,
+    pub processedAt: Option<u32>,
+    pub milliSilverMoved: u32,
+    pub fromPlanet: Planet,
+    pub toPlanet: Planet,
+    pub player: Player,
+}
+
+impl Arrival {
+    /// seconds between departure and arrival
+    pub fn duration(&self) -> u32 {
+        self.arrivalTime.saturating_sub(self.departureTime)
+    }
+
+    /// Distance travelled in game units. The contract sets travel time to
+    /// `dist * 100 / speed * 100 / TIME_FACTOR_HUNDREDTHS`, this undoes it.
+    pub fn distance(&self, time_factor_hundredths: u64) -> u64 {
+        self.duration() as u64 * self.fromPlanet.speed as u64 * time_factor_hundredths / (100 * 100)
+    }
+}
+
+impl Entity for Arrival {
+    const COLLECTION: &'static str = "arrivals";
+    const FRAGMENT: &'static str = "ArrivalFields";
+
+    fn fragments() -> Vec<&'static str> {
+        vec![ARRIVAL_FIELDS, PLAYER_FIELDS, PLANET_FIELDS]
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use super::planets::{Planet, PLANET_FIELDS};
+use super::players::{Player, PLAYER_FIELDS};
+use super::{Entity, Timestamped};
+
+pub static ARTIFACT_FIELDS: &str = include_str!("artifacts.graphql");
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Artifact {
+    pub id: String,
+    pub planetLevel: u32,
+    pub rarity: String,
+    pub discoverer: Player,
+    pub planetDiscoveredOn: Planet,
+    pub mintedAtTimestamp: u32,
+}
+
+impl Artifact {
+    /// The artifact's token id, which the subgraph keeps as hex like planet ids.
+    pub fn token_id(&self) -> Option<web3::types::U256> {
+        web3::types::U256::from_str_radix(self.id.trim_start_matches("0x"), 16).ok()
+    }
+}
+
+impl Entity for Artifact {
+    const COLLECTION: &'static str = "artifacts";
+    const FRAGMENT: &'static str = "ArtifactFields";
+
+    fn fragments() -> Vec<&'static str> {
+        vec![ARTIFACT_FIELDS, PLAYER_FIELDS, PLANET_FIELDS]
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
+
+impl Timestamped for Artifact {
+    const TIMESTAMP: &'static str = "mintedAtTimestamp";
+
+    fn timestamp(&self) -> u32 {
+        self.mintedAtTimestamp
+    }
+}
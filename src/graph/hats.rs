@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use super::planets::{Planet, PLANET_FIELDS};
+use super::players::{Player, PLAYER_FIELDS};
+use super::{Entity, Timestamped};
+
+pub static HAT_FIELDS: &str = include_str!("hats.graphql");
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Hat {
+    pub id: String,
+    pub planet: Planet,
+    pub player: Player,
+    pub hatLevel: u32,
+    pub timestamp: u32,
+}
+
+impl Entity for Hat {
+    const COLLECTION: &'static str = "hats";
+    const FRAGMENT: &'static str = "HatFields";
+
+    fn fragments() -> Vec<&'static str> {
+        vec![HAT_FIELDS, PLANET_FIELDS, PLAYER_FIELDS]
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
+
+impl Timestamped for Hat {
+    const TIMESTAMP: &'static str = "timestamp";
+
+    fn timestamp(&self) -> u32 {
+        self.timestamp
+    }
+}
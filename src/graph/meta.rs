@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{query, GraphError};
+use crate::config::Round;
+
+pub static META_FIELDS: &str = include_str!("meta.graphql");
+
+static GRAPH_META_QUERY: &str = r#"
+query sophon_meta {
+    graph_meta: _meta {
+        ...GraphMetaFields
+    }
+}
+"#;
+
+static META_QUERY: &str = r#"
+query sophon($block: Int!) {
+    df_meta: meta(block: {number: $block}, id: 0) {
+        ...DarkForestMetaFields
+    }
+    graph_meta: _meta(block: {number: $block}) {
+        ...GraphMetaFields
+    }
+}
+"#;
+
+/// Where the subgraph is at. Collection cycles pin to its latest indexed block
+/// so graph and contract reads describe the same moment.
+pub async fn query_graph_meta(round: &Round) -> Result<GraphMeta, GraphError> {
+    let query_text = format!("{}{}", GRAPH_META_QUERY, META_FIELDS);
+    let data = query::<GraphMetaQueryData>(round, query_text.as_str(), json!({})).await?;
+
+    Ok(data.graph_meta)
+}
+
+/// Both metas as of `block`
+pub async fn query_metas(round: &Round, block: u64) -> Result<MetaQueryData, GraphError> {
+    let query_text = format!("{}{}", META_QUERY, META_FIELDS);
+    query::<MetaQueryData>(round, query_text.as_str(), json!({ "block": block })).await
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GraphMeta {
+    pub hasIndexingErrors: bool,
+    pub deployment: String,
+    pub block: Block,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DarkForestMeta {
+    pub lastProcessed: u32,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Block {
+    pub number: u32,
+    /// graph-node leaves it null when it doesn't have it
+    pub hash: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GraphMetaQueryData {
+    pub graph_meta: GraphMeta,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetaQueryData {
+    pub graph_meta: GraphMeta,
+    pub df_meta: DarkForestMeta,
+}
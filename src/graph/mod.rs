@@ -0,0 +1,802 @@
+use reqwest::header;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::collections::HashSet;
+use tokio_compat_02::FutureExt;
+
+use crate::config::Round;
+
+mod arrivals;
+pub use arrivals::*;
+
+mod artifacts;
+pub use artifacts::*;
+
+mod hats;
+pub use hats::*;
+
+mod meta;
+pub use meta::*;
+
+mod planets;
+pub use planets::*;
+
+mod players;
+pub use players::*;
+
+/// most results The Graph will hand back in one page
+const PAGE_SIZE: usize = 1000;
+
+/// Everything a graph collection cycle needs, every entity fetched in full up
+/// to `max` results each. Hats and artifacts are only the ones after their
+/// cursor, oldest first.
+pub async fn query_graph(
+    round: &Round,
+    hat_cursor: &Cursor,
+    artifact_cursor: &Cursor,
+    block: u64,
+    max: usize,
+) -> Result<SophonQueryData, GraphError> {
+    let metas = query_metas(round, block).await?;
+
+    // pages come back in id order, put each entity back in the order we use it in
+    let in_flight = round.mapping.arrivals_in_flight.as_str();
+    let mut arrivals = query_all::<Arrival>(round, in_flight, json!({}), block, max).await?;
+    arrivals.sort_by_key(|arrival| arrival.arrivalTime);
+
+    let hats = query_since::<Hat>(round, hat_cursor, block, max).await?;
+    let artifacts = query_since::<Artifact>(round, artifact_cursor, block, max).await?;
+
+    Ok(SophonQueryData {
+        arrivals,
+        graph_meta: metas.graph_meta,
+        df_meta: metas.df_meta,
+        hats,
+        artifacts,
+    })
+}
+
+/// How far through a timestamped collection we've posted: the timestamp and id
+/// of the last entity seen. Ids break ties between entities sharing a timestamp.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Cursor {
+    pub timestamp: u32,
+    pub id: String,
+}
+
+impl Cursor {
+    /// A cursor that skips everything before now, for a collection we've never read.
+    pub fn now() -> Self {
+        Cursor {
+            timestamp: crate::profiles::now() as u32,
+            id: String::new(),
+        }
+    }
+
+    /// Whether the entity at `timestamp` with `id` comes after this cursor.
+    pub fn is_before(&self, timestamp: u32, id: &str) -> bool {
+        (self.timestamp, self.id.as_str()) < (timestamp, id)
+    }
+}
+
+/// A subgraph entity we can query by itself. Each one lives in its own module
+/// with a `.graphql` fragment selecting its fields, which the tests below
+/// check against schema.graphql and the entity's struct.
+pub trait Entity: DeserializeOwned {
+    /// collection to query, ie `arrivals`
+    const COLLECTION: &'static str;
+    /// name of the fragment selecting this entity's fields
+    const FRAGMENT: &'static str;
+
+    /// the entity's fragment followed by every fragment it spreads
+    fn fragments() -> Vec<&'static str>;
+
+    fn id(&self) -> &str;
+}
+
+/// An entity posted in the order it happened, read with `query_since`.
+pub trait Timestamped: Entity {
+    /// field holding when it happened, ie `timestamp`
+    const TIMESTAMP: &'static str;
+
+    fn timestamp(&self) -> u32;
+}
+
+/// Fetches the `T`s after `cursor` at `block`, oldest first, paging by timestamp
+/// so a result cut off at `max` never skips anything older than its last entity.
+pub async fn query_since<T: Timestamped>(
+    round: &Round,
+    cursor: &Cursor,
+    block: u64,
+    max: usize,
+) -> Result<Vec<T>, GraphError> {
+    let query_text = format!(
+        "query sophon_since($block: Int!, $first: Int!, $since: Int!) {{
+            page: {}(block: {{number: $block}}, first: $first, where: {{{}_gte: $since}}, orderBy: {}, orderDirection: asc) {{
+                ...{}
+            }}
+        }}
+        {}",
+        T::COLLECTION,
+        T::TIMESTAMP,
+        T::TIMESTAMP,
+        T::FRAGMENT,
+        T::fragments().join("\n")
+    );
+
+    let mut results: Vec<T> = vec![];
+    let mut seen = HashSet::new();
+    let mut since = cursor.timestamp;
+    let mut cut = false;
+
+    loop {
+        let variables = json!({ "block": block, "first": PAGE_SIZE, "since": since });
+        let page = query::<Page<T>>(round, query_text.as_str(), variables)
+            .await?
+            .page;
+
+        let full = page.len() == PAGE_SIZE;
+        let last = page.last().map(T::timestamp);
+        for entity in page {
+            if cursor.is_before(entity.timestamp(), entity.id())
+                && seen.insert(entity.id().to_string())
+            {
+                results.push(entity);
+            }
+        }
+
+        if !full {
+            break;
+        }
+        // a full page may end partway through a timestamp, so the next starts on it again
+        match last {
+            Some(last) if last > since && results.len() < max => since = last,
+            _ => {
+                cut = true;
+                break;
+            }
+        }
+    }
+
+    Ok(complete(results, cut, max))
+}
+
+/// Sorts `results` by timestamp and id. When they were `cut` short, the last
+/// timestamp may be missing some of its entities so it's left for next time,
+/// unless it's all there is. Then keeps at most `max`.
+fn complete<T: Timestamped>(mut results: Vec<T>, cut: bool, max: usize) -> Vec<T> {
+    results.sort_by(|a, b| (a.timestamp(), a.id()).cmp(&(b.timestamp(), b.id())));
+
+    if cut {
+        if let Some(last) = results.last().map(T::timestamp) {
+            let whole = results.iter().position(|entity| entity.timestamp() == last);
+            if let Some(whole) = whole.filter(|whole| *whole > 0) {
+                results.truncate(whole);
+            }
+        }
+    }
+
+    results.truncate(max);
+    results
+}
+
+/// Fetches every `T` matching `filter` at `block` from `round`'s subgraph, a page at a time by walking
+/// `id_gt`, so results are never cut off at The Graph's page size. Stops after
+/// `max` results. `variables` are extra filter arguments, declared by the
+/// type of their JSON value.
+pub async fn query_all<T: Entity>(
+    round: &Round,
+    filter: &str,
+    variables: serde_json::Value,
+    block: u64,
+    max: usize,
+) -> Result<Vec<T>, GraphError> {
+    let mut declarations = String::new();
+    if let Some(variables) = variables.as_object() {
+        for (name, value) in variables {
+            declarations.push_str(format!(", ${}: {}", name, graphql_type(value)).as_str());
+        }
+    }
+
+    let query_text = format!(
+        "query sophon_page($block: Int!, $first: Int!, $last_id: ID!{}) {{
+            page: {}(block: {{number: $block}}, first: $first, where: {{id_gt: $last_id, {}}}, orderBy: id, orderDirection: asc) {{
+                ...{}
+            }}
+        }}
+        {}",
+        declarations,
+        T::COLLECTION,
+        filter,
+        T::FRAGMENT,
+        T::fragments().join("\n")
+    );
+
+    let mut results: Vec<T> = vec![];
+    let mut last_id = String::new();
+
+    while results.len() < max {
+        let first = std::cmp::min(PAGE_SIZE, max - results.len());
+
+        let mut page_variables = variables.clone();
+        page_variables["block"] = json!(block);
+        page_variables["first"] = json!(first);
+        page_variables["last_id"] = json!(last_id);
+
+        let page = query::<Page<T>>(round, query_text.as_str(), page_variables)
+            .await?
+            .page;
+
+        let done = page.len() < first;
+        if let Some(last) = page.last() {
+            last_id = last.id().to_string();
+        }
+        results.extend(page);
+
+        if done {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+/// The GraphQL type a filter variable is declared as, going by its JSON value,
+/// ie `Int!` for a number and `[String!]!` for a list of addresses.
+fn graphql_type(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Bool(_) => "Boolean!".to_string(),
+        serde_json::Value::Number(_) => "Int!".to_string(),
+        serde_json::Value::Array(values) => match values.first() {
+            Some(value) => format!("[{}]!", graphql_type(value)),
+            None => "[String!]!".to_string(),
+        },
+        _ => "String!".to_string(),
+    }
+}
+
+/// Runs one query, surfacing anything in the GraphQL `errors` array as
+/// `GraphError::QueryError` rather than a confusing parse failure.
+async fn query<T: DeserializeOwned>(
+    round: &Round,
+    query_text: &str,
+    variables: serde_json::Value,
+) -> Result<T, GraphError> {
+    let body = json!({
+        "query": query_text,
+        "variables": variables
+    });
+    let response = post(round.subgraph_url.as_str(), body).await?;
+
+    let response =
+        serde_json::from_str::<GraphResponse<T>>(response.as_str()).map_err(GraphError::from)?;
+
+    if !response.errors.is_empty() {
+        let messages = response.errors.into_iter().map(|e| e.message).collect();
+        return Err(GraphError::QueryError(messages));
+    }
+
+    response.data.ok_or(GraphError::JsonError)
+}
+
+async fn post(url: &str, body: serde_json::Value) -> Result<String, GraphError> {
+    let mut headers = header::HeaderMap::new();
+    headers.insert(
+        "Content-Type",
+        "application/json".parse().map_err(GraphError::from)?,
+    );
+
+    let body = serde_json::to_string(&body).map_err(GraphError::from)?;
+
+    let response = reqwest::Client::new()
+        .post(url)
+        .headers(headers)
+        .body(body)
+        .send()
+        .compat()
+        .await
+        .map_err(GraphError::from)?
+        .text()
+        .await
+        .map_err(GraphError::from)?;
+
+    Ok(response)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GraphResponse<T> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub errors: Vec<GraphQLError>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GraphQLError {
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Page<T> {
+    pub page: Vec<T>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SophonQueryData {
+    pub arrivals: Vec<Arrival>,
+    pub graph_meta: GraphMeta,
+    pub df_meta: DarkForestMeta,
+    pub hats: Vec<Hat>,
+    pub artifacts: Vec<Artifact>,
+}
+
+#[derive(Debug)]
+pub enum GraphError {
+    Internal,
+    Stale,
+    JsonError,
+    HttpError,
+    /// the subgraph answered with GraphQL errors
+    QueryError(Vec<String>),
+}
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::QueryError(messages) => write!(f, "QueryError: {}", messages.join("; ")),
+            err => write!(f, "{:?}", err),
+        }
+    }
+}
+
+impl From<reqwest::Error> for GraphError {
+    fn from(_err: reqwest::Error) -> Self {
+        GraphError::HttpError
+    }
+}
+
+impl From<reqwest::header::InvalidHeaderValue> for GraphError {
+    fn from(_err: reqwest::header::InvalidHeaderValue) -> Self {
+        GraphError::Internal
+    }
+}
+
+impl From<serde_json::Error> for GraphError {
+    fn from(_err: serde_json::Error) -> Self {
+        GraphError::JsonError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::{self, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, Visitor};
+    use std::cell::Cell;
+    use std::collections::{BTreeSet, HashMap};
+    use std::iter::Peekable;
+
+    /// the subgraph's schema, fragments are checked against its types
+    static SCHEMA: &str = include_str!("schema.graphql");
+
+    /// One selected field, or a `...Fragment` spread with no selection.
+    struct Selection {
+        name: String,
+        selection: Vec<Selection>,
+    }
+
+    struct Fragment {
+        on: String,
+        selection: Vec<Selection>,
+    }
+
+    /// A schema field's named type, ie `Planet` for `[Planet!]!`.
+    struct Field {
+        named: String,
+        nullable: bool,
+    }
+
+    /// graphql type name to its fields
+    type Schema = HashMap<String, HashMap<String, Field>>;
+
+    fn tokens(text: &str) -> Vec<String> {
+        let mut tokens = vec![];
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '#' {
+                chars.by_ref().take_while(|c| *c != '\n').for_each(drop);
+            } else if c.is_alphanumeric() || c == '_' || c == '.' {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_' || (c == '.' && next == '.')) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                tokens.push(token);
+            } else if !c.is_whitespace() {
+                tokens.push(c.to_string());
+            }
+        }
+        tokens
+    }
+
+    /// Skips tokens up to and including `end`.
+    fn skip<I: Iterator<Item = String>>(tokens: &mut Peekable<I>, end: &str) {
+        tokens
+            .by_ref()
+            .take_while(|token| token != end)
+            .for_each(drop);
+    }
+
+    /// Fields up to the closing brace, skipping arguments.
+    fn selection<I: Iterator<Item = String>>(tokens: &mut Peekable<I>) -> Vec<Selection> {
+        let mut fields: Vec<Selection> = vec![];
+        while let Some(token) = tokens.next() {
+            match token.as_str() {
+                "}" => break,
+                "{" => {
+                    let inner = selection(tokens);
+                    if let Some(field) = fields.last_mut() {
+                        field.selection = inner;
+                    }
+                }
+                "(" => skip(tokens, ")"),
+                "..." => {
+                    let name = tokens.next().unwrap_or_default();
+                    fields.push(Selection {
+                        name: format!("...{}", name),
+                        selection: vec![],
+                    });
+                }
+                name => fields.push(Selection {
+                    name: name.to_string(),
+                    selection: vec![],
+                }),
+            }
+        }
+        fields
+    }
+
+    /// Every fragment defined across the graph module's `.graphql` files.
+    fn fragments() -> HashMap<String, Fragment> {
+        let text = [
+            ARRIVAL_FIELDS,
+            ARTIFACT_FIELDS,
+            HAT_FIELDS,
+            META_FIELDS,
+            PLANET_FIELDS,
+            PLAYER_FIELDS,
+            PLAYER_PROFILE_FIELDS,
+        ]
+        .concat();
+
+        let mut fragments = HashMap::new();
+        let mut tokens = tokens(&text).into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            if token == "fragment" {
+                let name = tokens.next().unwrap_or_default();
+                tokens.next(); // on
+                let on = tokens.next().unwrap_or_default();
+                skip(&mut tokens, "{");
+                let selection = selection(&mut tokens);
+                fragments.insert(name, Fragment { on, selection });
+            }
+        }
+        fragments
+    }
+
+    /// The object types in schema.graphql, enums and scalars are left out.
+    fn schema() -> Schema {
+        let mut schema = Schema::new();
+        let mut tokens = tokens(SCHEMA).into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token.as_str() {
+                "enum" => skip(&mut tokens, "}"),
+                "type" => {
+                    let name = tokens.next().unwrap_or_default();
+                    skip(&mut tokens, "{");
+
+                    let mut fields = HashMap::new();
+                    while let Some(field) = tokens.next() {
+                        if field == "}" {
+                            break;
+                        }
+                        if tokens.peek().map(String::as_str) == Some("(") {
+                            skip(&mut tokens, ")");
+                        }
+                        tokens.next(); // :
+
+                        // wrapping lists and ! around the named type, the last ! is the field's own
+                        let mut named = String::new();
+                        let mut last = String::new();
+                        while let Some(token) = tokens.peek().cloned() {
+                            match token.as_str() {
+                                "[" | "]" | "!" => {}
+                                _ if named.is_empty() => named = token.clone(),
+                                _ => break,
+                            }
+                            last = token;
+                            tokens.next();
+                        }
+                        while tokens.peek().map(String::as_str) == Some("@") {
+                            tokens.next();
+                            tokens.next();
+                            if tokens.peek().map(String::as_str) == Some("(") {
+                                skip(&mut tokens, ")");
+                            }
+                        }
+
+                        let nullable = last != "!";
+                        fields.insert(field, Field { named, nullable });
+                    }
+                    schema.insert(name, fields);
+                }
+                _ => {}
+            }
+        }
+        schema
+    }
+
+    /// Checks every field of `selection` exists on the schema's `on` type, and
+    /// object fields, and only those, select subfields.
+    fn validate(
+        schema: &Schema,
+        fragments: &HashMap<String, Fragment>,
+        on: &str,
+        selection: &[Selection],
+    ) {
+        let fields = schema
+            .get(on)
+            .unwrap_or_else(|| panic!("schema has no type {}", on));
+
+        for field in selection {
+            if let Some(spread) = field.name.strip_prefix("...") {
+                let fragment = &fragments[spread];
+                assert_eq!(fragment.on, on, "{} spread on {}", spread, on);
+                validate(schema, fragments, on, &fragment.selection);
+                continue;
+            }
+
+            let schema_field = fields
+                .get(&field.name)
+                .unwrap_or_else(|| panic!("{} has no field {}", on, field.name));
+            if schema.contains_key(&schema_field.named) {
+                assert!(
+                    !field.selection.is_empty(),
+                    "{}.{} needs a selection",
+                    on,
+                    field.name
+                );
+                validate(schema, fragments, &schema_field.named, &field.selection);
+            } else {
+                assert!(
+                    field.selection.is_empty(),
+                    "{}.{} is a scalar",
+                    on,
+                    field.name
+                );
+            }
+        }
+    }
+
+    /// Field names in `selection`, with spreads expanded.
+    fn selected(
+        fragments: &HashMap<String, Fragment>,
+        selection: &[Selection],
+    ) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        for field in selection {
+            match field.name.strip_prefix("...") {
+                Some(spread) => {
+                    let spread = fragments
+                        .get(spread)
+                        .unwrap_or_else(|| panic!("no fragment {}", spread));
+                    names.extend(selected(fragments, &spread.selection));
+                }
+                None => {
+                    names.insert(field.name.clone());
+                }
+            }
+        }
+        names
+    }
+
+    /// Stands in for a struct, and for one of its fields' values, to see how
+    /// serde's derive asks for them. Every answer is an error, only what was
+    /// asked for is kept.
+    enum Probe<'a> {
+        Fields(&'a Cell<&'static [&'static str]>),
+        Field(&'static str, &'a Cell<bool>),
+        Value(&'a Cell<bool>),
+    }
+
+    impl<'de, 'a> Deserializer<'de> for Probe<'a> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("probed"))
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            if let Probe::Value(optional) = self {
+                optional.set(true);
+            }
+            Err(de::Error::custom("probed"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            match self {
+                Probe::Fields(found) => {
+                    found.set(fields);
+                    Err(de::Error::custom("probed"))
+                }
+                Probe::Field(field, optional) => visitor.visit_map(OneField(Some(field), optional)),
+                Probe::Value(_) => Err(de::Error::custom("probed")),
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    /// A struct body holding just one field, whose value is probed.
+    struct OneField<'a>(Option<&'static str>, &'a Cell<bool>);
+
+    impl<'de, 'a> MapAccess<'de> for OneField<'a> {
+        type Error = de::value::Error;
+
+        fn next_key_seed<K: DeserializeSeed<'de>>(
+            &mut self,
+            seed: K,
+        ) -> Result<Option<K::Value>, Self::Error> {
+            match self.0.take() {
+                Some(field) => seed.deserialize(field.into_deserializer()).map(Some),
+                None => Ok(None),
+            }
+        }
+
+        fn next_value_seed<V: DeserializeSeed<'de>>(
+            &mut self,
+            seed: V,
+        ) -> Result<V::Value, Self::Error> {
+            seed.deserialize(Probe::Value(self.1))
+        }
+    }
+
+    /// The field names `T` deserializes from.
+    fn struct_fields<T: DeserializeOwned>() -> &'static [&'static str] {
+        let fields = Cell::new(&[][..]);
+        let _ = T::deserialize(Probe::Fields(&fields));
+        fields.get()
+    }
+
+    /// Whether `T` takes null for `field`.
+    fn optional<T: DeserializeOwned>(field: &'static str) -> bool {
+        let optional = Cell::new(false);
+        let _ = T::deserialize(Probe::Field(field, &optional));
+        optional.get()
+    }
+
+    /// The selections are valid on the schema's `on` type, every field they
+    /// select is on `T` and every field on `T` is selected, and `T` takes
+    /// null wherever the schema allows it.
+    fn check<T: DeserializeOwned>(on: &str, selections: &[&[Selection]]) {
+        let schema = schema();
+        let fragments = fragments();
+
+        let mut selection = BTreeSet::new();
+        for fields in selections {
+            validate(&schema, &fragments, on, fields);
+            selection.extend(selected(&fragments, fields));
+        }
+
+        let fields = struct_fields::<T>();
+        let names = fields.iter().map(|field| field.to_string()).collect();
+        assert_eq!(selection, names, "{}", on);
+
+        for field in fields {
+            if schema[on][*field].nullable {
+                assert!(optional::<T>(field), "{}.{} can be null", on, field);
+            }
+        }
+    }
+
+    #[test]
+    fn fragments_match_schema_and_structs() {
+        let fragments = fragments();
+        let fragment = |name: &str| {
+            let fragment = &fragments[name];
+            (fragment.on.clone(), fragment.selection.as_slice())
+        };
+
+        let (on, arrival) = fragment("ArrivalFields");
+        check::<Arrival>(&on, &[arrival]);
+        let (on, artifact) = fragment("ArtifactFields");
+        check::<Artifact>(&on, &[artifact]);
+        let (on, hat) = fragment("HatFields");
+        check::<Hat>(&on, &[hat]);
+        let (on, planet) = fragment("PlanetFields");
+        check::<Planet>(&on, &[planet]);
+        let (on, player) = fragment("PlayerFields");
+        let (_, profile) = fragment("PlayerProfileFields");
+        check::<Player>(&on, &[player, profile]);
+        let (on, graph_meta) = fragment("GraphMetaFields");
+        check::<GraphMeta>(&on, &[graph_meta]);
+        let (on, meta) = fragment("DarkForestMetaFields");
+        check::<DarkForestMeta>(&on, &[meta]);
+    }
+
+    #[test]
+    fn nested_selections_match_schema_and_structs() {
+        let fragments = fragments();
+        let block = fragments["GraphMetaFields"]
+            .selection
+            .iter()
+            .find(|field| field.name == "block")
+            .expect("no block in GraphMetaFields");
+
+        check::<Block>("_Block_", &[&block.selection]);
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Sighting(u32, String);
+
+    impl Entity for Sighting {
+        const COLLECTION: &'static str = "sightings";
+        const FRAGMENT: &'static str = "SightingFields";
+
+        fn fragments() -> Vec<&'static str> {
+            vec![]
+        }
+
+        fn id(&self) -> &str {
+            self.1.as_str()
+        }
+    }
+
+    impl Timestamped for Sighting {
+        const TIMESTAMP: &'static str = "timestamp";
+
+        fn timestamp(&self) -> u32 {
+            self.0
+        }
+    }
+
+    fn sightings(ids: &[(u32, &str)]) -> Vec<Sighting> {
+        ids.iter()
+            .map(|(timestamp, id)| Sighting(*timestamp, id.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn cut_results_end_on_a_whole_timestamp() {
+        let results = sightings(&[(2, "b"), (1, "c"), (2, "a"), (1, "a")]);
+        assert_eq!(
+            complete(results, true, 10),
+            sightings(&[(1, "a"), (1, "c")])
+        );
+
+        let results = sightings(&[(2, "b"), (1, "c"), (2, "a"), (1, "a")]);
+        assert_eq!(
+            complete(results, false, 3),
+            sightings(&[(1, "a"), (1, "c"), (2, "a")])
+        );
+
+        // one timestamp with more than a page is kept rather than never moving on
+        let results = sightings(&[(2, "b"), (2, "a")]);
+        assert_eq!(
+            complete(results, true, 10),
+            sightings(&[(2, "a"), (2, "b")])
+        );
+    }
+}
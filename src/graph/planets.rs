@@ -0,0 +1,157 @@
+use num_bigint::BigUint;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+
+use super::players::{Player, PLAYER_FIELDS};
+use super::{query, Entity, GraphError};
+use crate::config::Round;
+
+pub static PLANET_FIELDS: &str = include_str!("planets.graphql");
+
+static PLANET_QUERY: &str = r#"
+query sophon_planet($id: ID!, $block: Int!) {
+    planet(id: $id, block: {number: $block}) {
+        ...PlanetFields
+    }
+}
+"#;
+
+/// owner of planets nobody has captured yet
+pub static NO_OWNER: &str = "0x0000000000000000000000000000000000000000";
+
+/// The subgraph's id for the planet at `location`, unprefixed and zero padded hex.
+pub fn planet_id(location: &web3::types::U256) -> String {
+    // U256's LowerHex ignores the width, so pad by hand
+    format!("{:0>64}", format!("{:x}", location))
+}
+
+/// One planet as it was at `block`, None if nobody had found it yet.
+pub async fn query_planet(
+    round: &Round,
+    id: &str,
+    block: u64,
+) -> Result<Option<Planet>, GraphError> {
+    let query_text = [PLANET_QUERY, PLANET_FIELDS, PLAYER_FIELDS].concat();
+    let variables = json!({ "id": id, "block": block });
+
+    let data = query::<PlanetQueryData>(round, query_text.as_str(), variables).await?;
+
+    Ok(data.planet)
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Planet {
+    pub id: String,
+    #[serde(deserialize_with = "big_uint")]
+    pub locationDec: BigUint,
+    pub isInitialized: bool,
+    pub createdAt: u32,
+    pub lastUpdated: u32,
+    pub perlin: u32,
+    pub range: u32,
+    pub speed: u32,
+    pub defense: u32,
+    pub milliEnergyLazy: u32,
+    pub milliEnergyCap: u32,
+    pub milliEnergyGrowth: u32,
+    pub milliSilverCap: u32,
+    pub milliSilverGrowth: u32,
+    pub milliSilverLazy: u32,
+    pub planetLevel: u32,
+    pub rangeUpgrades: u32,
+    pub speedUpgrades: u32,
+    pub defenseUpgrades: u32,
+    pub ismilliEnergyCapBoosted: bool,
+    pub isSpeedBoosted: bool,
+    pub isDefenseBoosted: bool,
+    pub isRangeBoosted: bool,
+    pub ismilliEnergyGrowthBoosted: bool,
+    pub hatLevel: u32,
+    pub planetResource: String,
+    pub spaceType: String,
+    pub milliSilverSpent: u32,
+    pub owner: Player,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlanetQueryData {
+    pub planet: Option<Planet>,
+}
+
+impl Planet {
+    pub fn is_silver_mine(&self) -> bool {
+        self.planetResource == "SILVER"
+    }
+
+    /// the space type the way the game talks about it
+    pub fn space(&self) -> &'static str {
+        match self.spaceType.as_str() {
+            "NEBULA" => "nebula",
+            "DEEP_SPACE" => "deep space",
+            _ => "space",
+        }
+    }
+
+    /// ie `lvl 4 silver mine in deep space`
+    pub fn describe(&self) -> String {
+        let kind = if self.is_silver_mine() {
+            "silver mine"
+        } else {
+            "planet"
+        };
+
+        format!("lvl {} {} in {}", self.planetLevel, kind, self.space())
+    }
+}
+
+// the graph sends BigInts as decimal strings, state files hold the
+// num-bigint serde form
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BigUintRepr {
+    Decimal(String),
+    Digits(BigUint),
+}
+
+fn big_uint<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigUint, D::Error> {
+    match BigUintRepr::deserialize(deserializer)? {
+        BigUintRepr::Decimal(decimal) => decimal.parse().map_err(serde::de::Error::custom),
+        BigUintRepr::Digits(digits) => Ok(digits),
+    }
+}
+
+impl Entity for Planet {
+    const COLLECTION: &'static str = "planets";
+    const FRAGMENT: &'static str = "PlanetFields";
+
+    fn fragments() -> Vec<&'static str> {
+        vec![PLANET_FIELDS, PLAYER_FIELDS]
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::types::U256;
+
+    #[test]
+    fn planet_id_keeps_leading_zeros() {
+        let location = U256::from_str_radix(
+            "00000a3f9c2e0b7d11c4a0b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f6",
+            16,
+        )
+        .unwrap();
+
+        assert_eq!(
+            planet_id(&location),
+            "00000a3f9c2e0b7d11c4a0b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f6"
+        );
+        assert_eq!(planet_id(&U256::from(1)), format!("{:0>64}", "1"));
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::planets::{Planet, PLANET_FIELDS};
+use super::{query, Entity, GraphError};
+use crate::config::Round;
+
+pub static PLAYER_FIELDS: &str = include_str!("players.graphql");
+
+/// a player and their home world, what query_player and query_all read
+pub static PLAYER_PROFILE_FIELDS: &str = include_str!("player_profile.graphql");
+
+static PLAYER_QUERY: &str = r#"
+query sophon_player($id: ID!, $block: Int!) {
+    player(id: $id, block: {number: $block}) {
+        ...PlayerProfileFields
+    }
+}
+"#;
+
+/// One player with their home world, None if they haven't joined. What they
+/// own is paged separately, a nested selection is capped at 1000.
+pub async fn query_player(
+    round: &Round,
+    id: &str,
+    block: u64,
+) -> Result<Option<Player>, GraphError> {
+    let query_text = [
+        PLAYER_QUERY,
+        PLAYER_PROFILE_FIELDS,
+        PLAYER_FIELDS,
+        PLANET_FIELDS,
+    ]
+    .concat();
+    let variables = json!({ "id": id, "block": block });
+
+    let data = query::<PlayerQueryData>(round, query_text.as_str(), variables).await?;
+
+    Ok(data.player)
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Player {
+    pub id: String,
+    pub initTimestamp: u32,
+    // only filled in by query_player and query_all
+    pub homeWorld: Option<Box<Planet>>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerQueryData {
+    pub player: Option<Player>,
+}
+
+impl Entity for Player {
+    const COLLECTION: &'static str = "players";
+    const FRAGMENT: &'static str = "PlayerProfileFields";
+
+    fn fragments() -> Vec<&'static str> {
+        vec![PLAYER_PROFILE_FIELDS, PLAYER_FIELDS, PLANET_FIELDS]
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::config::SophonConfig;
+use crate::cycle::{scan_every_cycle, Scan};
+use crate::names::player_name;
+use crate::node::*;
+use crate::profiles::now;
+use crate::twitter::{send_thread, Post};
+use crate::{save_state, SophonError, SophonShare, SophonState, STATE_FILE};
+
+/// where ended rounds' state files are kept, one directory per round
+const ARCHIVE_DIR: &str = "sophon_archive";
+
+/// Where the followed round is in its life.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+pub enum Phase {
+    /// deployed but nobody has joined
+    #[default]
+    Waiting,
+    Running,
+    Paused,
+    Ended,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct LifecycleState {
+    pub phase: Phase,
+    /// last block scanned for the first PlayerInitialized, while Waiting
+    pub block: Option<u64>,
+    /// block the first civilization joined at, when we saw it happen
+    pub started_block: Option<u64>,
+    /// closing thread of an ended round, waiting on the hand over
+    pub closing: Vec<String>,
+}
+
+/// Follows the round from start to end. Posts an opening announcement at the
+/// first PlayerInitialized, notes pauses, and once the configured end time
+/// passes writes the closing stats and returns. Main then stops every
+/// collector before `hand_over` archives the round and moves on.
+pub async fn follow_round(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
+    if config.round().ends_at.is_none() {
+        eprintln!(
+            "sophon: round {} has no ends_at, its end won't be noticed",
+            config.round
+        );
+    }
+
+    scan_every_cycle::<LifecycleState>(wrapped_state, rpc, config).await
+}
+
+impl Scan for LifecycleState {
+    fn of(state: &SophonState) -> Self {
+        state.lifecycle.clone()
+    }
+
+    fn put(self, state: &mut SophonState) {
+        state.lifecycle = self;
+    }
+
+    async fn scan(
+        &mut self,
+        state: &SophonState,
+        rpc: &Rpc,
+        config: &SophonConfig,
+        block: u64,
+    ) -> Result<Vec<Post>, SophonError> {
+        cycle(self, state, rpc, config, block).await
+    }
+
+    fn finished(&self) -> bool {
+        self.phase == Phase::Ended
+    }
+}
+
+async fn cycle(
+    lifecycle: &mut LifecycleState,
+    state: &SophonState,
+    rpc: &Rpc,
+    config: &SophonConfig,
+    block: u64,
+) -> Result<Vec<Post>, SophonError> {
+    let round = config.round();
+    let mut posts = vec![];
+
+    if lifecycle.phase == Phase::Waiting {
+        // the first look only decides whether we missed the start
+        match lifecycle.block {
+            None => {
+                if df_players(rpc, block).await? > 0 {
+                    lifecycle.phase = Phase::Running;
+                }
+            }
+            Some(last) => {
+                let joined = df_events(rpc, "PlayerInitialized", last + 1, block).await?;
+                if let Some(first) = joined.first() {
+                    let player = first
+                        .params
+                        .iter()
+                        .find(|param| param.name == "player")
+                        .and_then(|param| param.value.clone().into_address());
+                    let player = match player {
+                        Some(player) => player_name(config, &format!("{:?}", player)),
+                        None => "a civilization".to_string(),
+                    };
+
+                    let tweet = format!(
+                        "Sophon 0c71e5b9 TX: round {} has begun, {} is the first civilization to enter the universe #darkforest",
+                        round.name, player
+                    );
+                    posts.push(Post::new(tweet, first.block));
+
+                    lifecycle.phase = Phase::Running;
+                    lifecycle.started_block = Some(first.block);
+                }
+            }
+        }
+        lifecycle.block = Some(block);
+    }
+
+    // collect_params posts the pause itself, this only tracks it
+    let paused = df_paused(rpc, block).await?;
+    lifecycle.phase = match lifecycle.phase {
+        Phase::Running if paused => Phase::Paused,
+        Phase::Paused if !paused => Phase::Running,
+        phase => phase,
+    };
+
+    if let Some(ends_at) = round.ends_at {
+        if now() >= ends_at && lifecycle.phase != Phase::Ended {
+            lifecycle.closing = close(state, rpc, config, block).await?;
+            lifecycle.phase = Phase::Ended;
+        }
+    }
+
+    Ok(posts)
+}
+
+/// Archives an ended round, switches the state over to the next configured
+/// round and posts the closing thread. Only called once nothing else is
+/// touching the state, returns the round moved on to, if any.
+pub async fn hand_over(state: &mut SophonState, config: &SophonConfig) -> Option<String> {
+    let closing = std::mem::take(&mut state.lifecycle.closing);
+
+    archive(state, &config.round);
+    let next = next_round(state, config);
+
+    save_state(state);
+
+    if !closing.is_empty() {
+        // only what didn't go out is queued, as standalone posts
+        if let Err((unsent, err)) = send_thread(closing).await {
+            eprintln!("sophon: closing announcement failed: {:?}", err);
+
+            for tweet in unsent {
+                state.tweets.push_back(Post {
+                    text: tweet,
+                    block: None,
+                });
+            }
+
+            save_state(state);
+        }
+    }
+
+    next
+}
+
+/// The closing thread, final stats read at `block`.
+async fn close(
+    state: &SophonState,
+    rpc: &Rpc,
+    config: &SophonConfig,
+    block: u64,
+) -> Result<Vec<String>, SophonError> {
+    let round = config.round();
+    let players = df_players(rpc, block).await?;
+    let radius = df_radius(rpc, block).await?;
+    let counts = df_counts(rpc, block).await?;
+
+    let totals = counts
+        .iter()
+        .enumerate()
+        .map(|(level, count)| format!("lvl{}:{}", level, count))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let tally = &state.digest.tally;
+
+    let mut thread = vec![
+        format!(
+            "Sophon 6b3e2f84 TX: round {} has ended. {} civilizations reached a universe of radius {} #darkforest",
+            round.name, players, radius
+        ),
+        format!(
+            "Sophon 6b3e2f84 TX: final planet totals: {} #darkforest",
+            totals
+        ),
+        format!(
+            "Sophon 6b3e2f84 TX: we watched {} moves carry {} silver, and {} hats and {} artifacts found #darkforest",
+            tally.moves, tally.silver_moved, tally.hats, tally.artifacts
+        ),
+    ];
+
+    match config.next_round() {
+        Some(next) => thread.push(format!(
+            "Sophon 6b3e2f84 TX: Sophon now turns to round {} #darkforest",
+            next.name
+        )),
+        None => thread.push("Sophon 6b3e2f84 TX: Sophon goes dark #darkforest".to_string()),
+    }
+
+    Ok(thread)
+}
+
+/// Keeps the ended round's whole state, digest history included, under ARCHIVE_DIR.
+fn archive(state: &SophonState, round: &str) {
+    let dir = format!("{}/{}", ARCHIVE_DIR, round);
+    let written = std::fs::create_dir_all(&dir).and_then(|_| {
+        let state_json = serde_json::to_string(state).unwrap_or_default();
+        std::fs::write(format!("{}/{}", dir, STATE_FILE), state_json)
+    });
+
+    if let Err(err) = written {
+        eprintln!("sophon: archiving round {} failed: {:?}", round, err);
+    }
+}
+
+/// Starts a fresh state for the next configured round, carrying over posts
+/// still waiting to go out. With no next round the ended state stays put.
+fn next_round(state: &mut SophonState, config: &SophonConfig) -> Option<String> {
+    let next = config.next_round()?;
+    *state = SophonState {
+        round: Some(next.name.clone()),
+        tweets: std::mem::take(&mut state.tweets),
+        ..Default::default()
+    };
+
+    Some(next.name.clone())
+}
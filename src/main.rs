@@ -1,46 +1,280 @@
 //! `cargo run consumer_key consumer_secret_key access_token secret_access_token`
 
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::time::sleep;
 use web3::futures::TryFutureExt;
 
+mod alert;
+
+mod anomaly;
+use anomaly::*;
+
+mod artifacts;
+use artifacts::*;
+
+mod captures;
+use captures::*;
+
+mod chain;
+use chain::*;
+
+mod config;
+use config::*;
+
+mod cycle;
+
+mod digest;
+use digest::*;
+
 mod graph;
 use graph::*;
 
+mod lifecycle;
+use lifecycle::*;
+
+mod names;
+use names::*;
+
 mod node;
 use node::*;
 
+mod params;
+use params::*;
+
+mod profiles;
+use profiles::*;
+
+mod records;
+use records::*;
+
+mod relations;
+use relations::*;
+
+mod supervisor;
+use supervisor::*;
+
 mod twitter;
 use twitter::*;
 
+mod upgrades;
+use upgrades::*;
+
+mod watch;
+use watch::*;
+
+mod whales;
+use whales::*;
+
 const STAGGER_DELAY: Duration = Duration::from_secs(60 * 60);
 const COLLECT_DELAY: Duration = Duration::from_secs(60 * 30);
 
+/// more hats or artifacts than this in one cycle are posted as a single summary
+const MAX_DISCOVERY_POSTS: usize = 3;
+
 const COUNTS_DELAY: Duration = Duration::from_secs(60 * 60 * 12);
 
 const STATE_FILE: &str = "sophon_state.json";
 
 #[tokio::main]
 async fn main() {
+    let config = load_config().expect("invalid sophon_config.json");
+
+    // each pass follows one round until it hands over to the next
+    while follow(config.clone()).await.is_some() {}
+}
+
+/// Runs every collector on the state file's round until ctrl-c, or until the
+/// round ends. Returns the round the state has moved on to, if any.
+async fn follow(mut config: SophonConfig) -> Option<String> {
     let state_json = std::fs::read_to_string(STATE_FILE).unwrap_or_default();
-    let state = serde_json::from_str::<SophonState>(state_json.as_str()).unwrap_or_default();
+    let mut state = serde_json::from_str::<SophonState>(state_json.as_str()).unwrap_or_default();
+
+    // once a round ends the state file follows the next configured round
+    if let Some(round) = &state.round {
+        config.round = round.clone();
+    }
+    if config.current_round().is_none() {
+        panic!("round {} isn't one of the configured rounds", config.round);
+    }
+
+    // stopped between the round ending and handing it over
+    if state.lifecycle.phase == Phase::Ended {
+        return ended(&mut state, &config).await;
+    }
+
     let wrapped_state = Arc::new(Mutex::new(SophonShare { state }));
+    let rpc = Arc::new(Rpc::new(&config).expect("invalid rpc config"));
+    let config = Arc::new(config);
 
     let ctrl_c = tokio::signal::ctrl_c().map_err(SophonError::from);
-
+    let lifecycle_state = wrapped_state.clone();
+    let lifecycle_rpc = rpc.clone();
+    let lifecycle_config = config.clone();
+    let hand_over_config = config.clone();
+    let lifecycle = move || {
+        follow_round(
+            lifecycle_state.clone(),
+            lifecycle_rpc.clone(),
+            lifecycle_config.clone(),
+        )
+    };
+
+    let graph_state = wrapped_state.clone();
+    let graph_rpc = rpc.clone();
+    let graph_config = config.clone();
+    let graph =
+        move || collect_from_graph(graph_state.clone(), graph_rpc.clone(), graph_config.clone());
+    let node_state = wrapped_state.clone();
+    let node_rpc = rpc.clone();
+    let node_config = config.clone();
+    let node = move || collect_from_node(node_state.clone(), node_rpc.clone(), node_config.clone());
+    let tweets_state = wrapped_state.clone();
+    let tweeter = move || tweets(tweets_state.clone());
+    let events_state = wrapped_state.clone();
+    let events_rpc = rpc.clone();
+    let events_config = config.clone();
+    let events = move || {
+        collect_round_events(
+            events_state.clone(),
+            events_rpc.clone(),
+            events_config.clone(),
+        )
+    };
+    let artifacts_state = wrapped_state.clone();
+    let artifacts_rpc = rpc.clone();
+    let artifacts_config = config.clone();
+    let artifacts = move || {
+        collect_artifacts(
+            artifacts_state.clone(),
+            artifacts_rpc.clone(),
+            artifacts_config.clone(),
+        )
+    };
+    let digest_state = wrapped_state.clone();
+    let digest_rpc = rpc.clone();
+    let digest_config = config.clone();
+    let digest = move || {
+        collect_digest(
+            digest_state.clone(),
+            digest_rpc.clone(),
+            digest_config.clone(),
+        )
+    };
+    let relations_state = wrapped_state.clone();
+    let relations_rpc = rpc.clone();
+    let relations_config = config.clone();
+    let relations = move || {
+        collect_relations(
+            relations_state.clone(),
+            relations_rpc.clone(),
+            relations_config.clone(),
+        )
+    };
+    let upgrades_state = wrapped_state.clone();
+    let upgrades_rpc = rpc.clone();
+    let upgrades_config = config.clone();
+    let upgrades = move || {
+        collect_upgrades(
+            upgrades_state.clone(),
+            upgrades_rpc.clone(),
+            upgrades_config.clone(),
+        )
+    };
+    let params_state = wrapped_state.clone();
+    let params_rpc = rpc.clone();
+    let params_config = config.clone();
+    let params = move || {
+        collect_params(
+            params_state.clone(),
+            params_rpc.clone(),
+            params_config.clone(),
+        )
+    };
+    let counts_state = wrapped_state.clone();
+    let counts_rpc = rpc.clone();
+    let counts = move || tweet_counts(counts_state.clone(), counts_rpc.clone(), config.clone());
+
+    // each collector runs as its own task so one failing can't take down the rest
     futures_micro::or!(
         ctrl_c,
-        collect_from_graph(wrapped_state.clone()), //COLLECT_DELAY
-        collect_from_node(wrapped_state.clone()),  //COLLECT_DELAY
-        tweets(wrapped_state.clone()),             //STAGGER_DELAY
-        tweet_counts(),                            //COUNTS_DELAY
+        collector(&rpc, "follow_round", lifecycle, supervise_to_end),
+        collector(&rpc, "collect_from_graph", graph, supervise), //COLLECT_DELAY
+        collector(&rpc, "collect_from_node", node, supervise),   //COLLECT_DELAY
+        collector(&rpc, "collect_round_events", events, supervise), //COLLECT_DELAY
+        collector(&rpc, "collect_artifacts", artifacts, supervise), //COLLECT_DELAY
+        collector(&rpc, "collect_digest", digest, supervise),    //COLLECT_DELAY
+        collector(&rpc, "collect_relations", relations, supervise), //COLLECT_DELAY
+        collector(&rpc, "collect_upgrades", upgrades, supervise), //COLLECT_DELAY
+        collector(&rpc, "collect_params", params, supervise),    //COLLECT_DELAY
+        supervise("tweets", tweeter),                            //STAGGER_DELAY
+        collector(&rpc, "tweet_counts", counts, supervise),      //COUNTS_DELAY
     )
     .await
     .unwrap();
+
+    // the collectors' tasks were aborted as they dropped, so the round can
+    // be handed over without them writing into the next one
+    let mut share = wrapped_state.lock().await;
+    if share.state.lifecycle.phase != Phase::Ended {
+        return None;
+    }
+    ended(&mut share.state, &hand_over_config).await
+}
+
+/// Runs `task` under `supervise`, unless the round's abi lacks something it
+/// reads, in which case it idles so the other collectors still run.
+fn collector<F, S, Fut>(
+    rpc: &Rpc,
+    name: &'static str,
+    task: F,
+    supervise: S,
+) -> impl Future<Output = Result<(), SophonError>>
+where
+    S: FnOnce(&'static str, F) -> Fut,
+    Fut: Future<Output = Result<(), SophonError>>,
+{
+    let enabled = rpc.enabled(name);
+    let supervised = supervise(name, task);
+
+    async move {
+        if !enabled {
+            return std::future::pending().await;
+        }
+        supervised.await
+    }
+}
+
+/// Hands an ended round over to the next one, None when there isn't one.
+async fn ended(state: &mut SophonState, config: &SophonConfig) -> Option<String> {
+    let next = hand_over(state, config).await;
+    if next.is_none() {
+        // the tweets collector stopped with the rest, so empty the queue here
+        let ctrl_c = tokio::signal::ctrl_c().map_err(SophonError::from);
+        let _ = futures_micro::or!(ctrl_c, drain(state)).await;
+
+        println!(
+            "sophon: round {} has ended, nothing left to follow",
+            config.round
+        );
+    }
+    next
+}
+
+/// Sends what's left in the queue at the usual pace.
+async fn drain(state: &mut SophonState) -> Result<(), SophonError> {
+    while !state.tweets.is_empty() {
+        send_next(state).await;
+
+        if !state.tweets.is_empty() {
+            sleep(STAGGER_DELAY).await;
+        }
+    }
+    Ok(())
 }
 
 // ctrlc returns an error so tweets has to in order to match
@@ -49,26 +283,47 @@ async fn tweets(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<(), SophonErro
         // scope for mutex release
         {
             let mut share = wrapped_state.lock().await;
+            send_next(&mut share.state).await;
+        }
 
-            // send a tweet if available
-            if let Some(tweet) = share.state.tweets.get(0) {
-                // if it sends successfully, pop it to remove it
-                if send(tweet.to_owned()).await.is_ok() {
-                    share.state.tweets.pop_front();
+        sleep(STAGGER_DELAY).await;
+    }
+}
 
-                    // mutated state so save to disk
-                    if let Ok(state_json) = serde_json::to_string(&share.state) {
-                        let _ = std::fs::write(STATE_FILE, state_json);
-                    }
+/// Sends the tweet at the front of the queue, if there is one.
+async fn send_next(state: &mut SophonState) {
+    if let Some(tweet) = state.tweets.front() {
+        // if it sends successfully, pop it to remove it
+        match send(tweet.text.clone()).await {
+            Ok(()) => {
+                if let Some(block) = tweet.block {
+                    println!("sophon: posted data from block {}", block);
                 }
+                state.tweets.pop_front();
+
+                // mutated state so save to disk
+                save_state(state);
             }
-        }
+            Err(TwitterError::Rejected(err)) => {
+                eprintln!("sophon: tweet rejected, dropping it: {}", err);
+                state.tweets.pop_front();
 
-        sleep(STAGGER_DELAY).await;
+                // mutated state so save to disk
+                save_state(state);
+            }
+            Err(TwitterError::HttpError(err)) => {
+                eprintln!("sophon: tweet failed: {}", err)
+            }
+            Err(err) => eprintln!("sophon: tweet failed: {:?}", err),
+        }
     }
 }
 
-async fn collect_from_graph(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<(), SophonError> {
+async fn collect_from_graph(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
     let mut dirty = false;
 
     loop {
@@ -76,8 +331,29 @@ async fn collect_from_graph(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<()
         {
             let mut share = wrapped_state.lock().await;
 
-            if let Ok(res) = query_graph(share.state.hat_level, share.state.planet_level).await {
+            // pin the cycle to the subgraph's latest block, if it can be trusted
+            let res = match pin_block(&mut share.state, &rpc, &config).await {
+                Some(block) => {
+                    // a first run starts from now rather than posting the whole round
+                    let state = &mut share.state;
+                    let hat_cursor = state.hat_cursor.get_or_insert_with(Cursor::now).clone();
+                    let artifact_cursor = state
+                        .artifact_cursor
+                        .get_or_insert_with(Cursor::now)
+                        .clone();
+                    let max = config.graph_max_results;
+                    query_graph(config.round(), &hat_cursor, &artifact_cursor, block, max).await
+                }
+                None => Err(GraphError::Stale),
+            };
+
+            if let Err(err @ GraphError::QueryError(_)) = &res {
+                eprintln!("sophon: graph query failed: {}", err);
+            }
+
+            if let Ok(res) = res {
                 dbg!(res.df_meta.clone());
+                let block = res.graph_meta.block.number as u64;
                 if !res.graph_meta.hasIndexingErrors {
                     if let Some(arrival) = res.arrivals.last() {
                         let significant = (arrival.arrivalId / 100000) * 100000;
@@ -87,7 +363,7 @@ async fn collect_from_graph(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<()
                                 significant
                             );
 
-                            share.state.tweets.push_back(tweet);
+                            share.state.tweets.push_back(Post::new(tweet, block));
 
                             share.state.significant_arrival = significant;
                             dirty = true;
@@ -96,90 +372,272 @@ async fn collect_from_graph(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<()
 
                     if res.arrivals.len() > share.state.most_arrivals_in_motion {
                         let tweet = format!(
-                        "Sophon ec1b89f9 TX: Unusually high activity: {} movements detected #darkforest",
+                        "Sophon ec1b89f9 TX: Record activity: {} movements detected #darkforest",
                         res.arrivals.len()
                     );
 
-                        share.state.tweets.push_back(tweet);
+                        share.state.tweets.push_back(Post::new(tweet, block));
 
                         share.state.most_arrivals_in_motion = res.arrivals.len();
                         dirty = true;
                     }
 
-                    if !res.hats.is_empty() {
+                    if watch_arrivals(&mut share.state, &config, &res.arrivals).await {
+                        dirty = true;
+                    }
+
+                    if res.hats.len() > MAX_DISCOVERY_POSTS {
+                        let best = res.hats.iter().max_by_key(|hat| hat.hatLevel).unwrap();
+                        let player = &best.player.id;
+                        let player =
+                            describe_player(&mut share.state, &config, player, block).await;
+
                         let tweet = format!(
-                            "Sophon c2463284 TX: {} has discovered lvl {} hat technology at {} #darkforest",
-                            res.hats[0].player.id, res.hats[0].hatLevel, res.hats[0].planet.id
+                            "Sophon c2463284 TX: {} hat technologies discovered, up to lvl {} by {} #darkforest",
+                            res.hats.len(), best.hatLevel, player
                         );
 
-                        share.state.tweets.push_back(tweet);
+                        share.state.tweets.push_back(Post::new(tweet, block));
+                    } else {
+                        for hat in &res.hats {
+                            let player = &hat.player.id;
+                            let player =
+                                describe_player(&mut share.state, &config, player, block).await;
 
-                        share.state.hat_level = res.hats[0].hatLevel;
+                            let tweet = format!(
+                                "Sophon c2463284 TX: {} has discovered lvl {} hat technology at {}, a {} #darkforest",
+                                player, hat.hatLevel, planet_name(&config, &hat.planet.id), hat.planet.describe()
+                            );
+
+                            share.state.tweets.push_back(Post::new(tweet, block));
+                        }
+                    }
+
+                    if let Some(hat) = res.hats.last() {
+                        share.state.hat_cursor = Some(Cursor {
+                            timestamp: hat.timestamp,
+                            id: hat.id.clone(),
+                        });
                         dirty = true;
                     }
 
-                    if !res.artifacts.is_empty() {
+                    if res.artifacts.len() > MAX_DISCOVERY_POSTS {
+                        // rarest first
+                        let mut by_rarity: Vec<(u32, &str, usize)> = vec![];
+                        for artifact in &res.artifacts {
+                            match by_rarity
+                                .iter_mut()
+                                .find(|(_, rarity, _)| *rarity == artifact.rarity)
+                            {
+                                Some((level, _, count)) => {
+                                    *level = std::cmp::max(*level, artifact.planetLevel);
+                                    *count += 1;
+                                }
+                                None => by_rarity.push((
+                                    artifact.planetLevel,
+                                    artifact.rarity.as_str(),
+                                    1,
+                                )),
+                            }
+                        }
+                        by_rarity.sort_by_key(|(level, _, _)| std::cmp::Reverse(*level));
+
+                        let totals = by_rarity
+                            .iter()
+                            .map(|(_, rarity, count)| format!("{} {}", count, rarity))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+
                         let tweet = format!(
-                            "Sophon a74b242f TX: {} artifact technology discovered at {} via {} #darkforest",
-                            res.artifacts[0].rarity, res.artifacts[0].planetDiscoveredOn.id, res.artifacts[0].discoverer.id,
+                            "Sophon a74b242f TX: {} artifact technologies discovered: {} #darkforest",
+                            res.artifacts.len(), totals
                         );
 
-                        share.state.tweets.push_back(tweet);
+                        share.state.tweets.push_back(Post::new(tweet, block));
+                    } else {
+                        for artifact in &res.artifacts {
+                            let discoverer = &artifact.discoverer.id;
+                            let discoverer =
+                                describe_player(&mut share.state, &config, discoverer, block).await;
 
-                        share.state.planet_level += 2;
+                            let tweet = format!(
+                                "Sophon a74b242f TX: {} artifact technology discovered at {}, a {}, via {} #darkforest",
+                                artifact.rarity, planet_name(&config, &artifact.planetDiscoveredOn.id), artifact.planetDiscoveredOn.describe(), discoverer,
+                            );
+
+                            share.state.tweets.push_back(Post::new(tweet, block));
+                        }
+                    }
+
+                    if let Some(artifact) = res.artifacts.last() {
+                        share.state.artifact_cursor = Some(Cursor {
+                            timestamp: artifact.mintedAtTimestamp,
+                            id: artifact.id.clone(),
+                        });
+                        dirty = true;
+                    }
+
+                    let tally = &mut share.state.digest.tally;
+                    tally.count_arrivals(&res.arrivals);
+                    tally.count_discoveries(res.hats.len(), res.artifacts.len());
+                    if !res.arrivals.is_empty() {
                         dirty = true;
                     }
 
-                    for arrival in res.arrivals {
-                        let mut length_tweets: Vec<String> = vec![];
+                    // winners of the day or week that just ended, before this cycle's offers
+                    let now = now();
+                    let closed = share.state.rolling.close(now);
+                    if !closed.is_empty() {
+                        share.state.tweets.extend(closed);
+                        dirty = true;
+                    }
 
-                        let longest_move = ((arrival.arrivalTime - arrival.departureTime) as f64
-                            / (arrival.fromPlanet.speed as f64 / 100.0))
-                            as u32;
+                    let label = "The busiest moment";
+                    let text = format!("{} movements in flight", res.arrivals.len());
+                    let rolling = &mut share.state.rolling;
+                    rolling.offer(
+                        "activity",
+                        res.arrivals.len() as u64,
+                        label,
+                        text,
+                        block,
+                        now,
+                    );
 
-                        if longest_move > share.state.longest_move {
-                            let tweet = format!(
-                                "Sophon eb4bc797 TX: Record interstellar voyage arriving in {} seconds via {} #darkforest",
-                                arrival.arrivalTime - arrival.departureTime,
-                                arrival.player.id,
+                    // only tweet the biggest move of the cycle
+                    if let Ok(time_factor) = df_time_factor(&rpc, block).await {
+                        let farthest = res
+                            .arrivals
+                            .iter()
+                            .max_by_key(|arrival| arrival.distance(time_factor));
+
+                        if let Some(arrival) = farthest {
+                            let distance = arrival.distance(time_factor);
+
+                            let text = format!(
+                                "{} units from {} to {} via {}",
+                                distance,
+                                planet_name(&config, &arrival.fromPlanet.id),
+                                planet_name(&config, &arrival.toPlanet.id),
+                                player_name(&config, &arrival.player.id),
                             );
-                            length_tweets.push(tweet);
-
-                            share.state.longest_move = longest_move;
+                            let label = "The longest voyage";
+                            let rolling = &mut share.state.rolling;
+                            rolling.offer("voyage", distance, label, text, block, now);
                             dirty = true;
+
+                            if distance > share.state.longest_distance {
+                                let player = &arrival.player.id;
+                                let player =
+                                    describe_player(&mut share.state, &config, player, block).await;
+
+                                let tweet = format!(
+                                    "Sophon eb4bc797 TX: Record interstellar voyage of {} units from {} to {} via {} #darkforest",
+                                    distance,
+                                    planet_name(&config, &arrival.fromPlanet.id),
+                                    planet_name(&config, &arrival.toPlanet.id),
+                                    player,
+                                );
+                                share.state.tweets.push_back(Post::new(tweet, block));
+
+                                share.state.longest_distance = distance;
+                                dirty = true;
+                            }
                         }
+                    }
+
+                    let slowest = res.arrivals.iter().max_by_key(|arrival| arrival.duration());
+                    if let Some(arrival) = slowest {
+                        if arrival.duration() > share.state.longest_duration {
+                            let player = &arrival.player.id;
+                            let player =
+                                describe_player(&mut share.state, &config, player, block).await;
+
+                            let tweet = format!(
+                                "Sophon 4f20d8b6 TX: Record voyage time of {} hours to {} via {} #darkforest",
+                                arrival.duration() / (60 * 60),
+                                planet_name(&config, &arrival.toPlanet.id),
+                                player,
+                            );
+                            share.state.tweets.push_back(Post::new(tweet, block));
 
-                        // only tweet the biggest move
-                        if let Some(tweet) = length_tweets.last() {
-                            share.state.tweets.push_back(tweet.to_string());
+                            share.state.longest_duration = arrival.duration();
                             dirty = true;
                         }
+                    }
+
+                    let whale = res
+                        .arrivals
+                        .iter()
+                        .max_by_key(|arrival| arrival.milliSilverMoved);
+                    if let Some(arrival) = whale {
+                        let silver = arrival.milliSilverMoved as u64 / 1000;
+                        let text = format!(
+                            "{} silver via {}",
+                            silver,
+                            player_name(&config, &arrival.player.id)
+                        );
+                        let label = "The most silver in one move";
+                        let rolling = &mut share.state.rolling;
+                        rolling.offer("whale", silver, label, text, block, now);
+                        dirty = true;
+                    }
+
+                    let motions = in_motion(&res.arrivals);
+                    let top = motions
+                        .iter()
+                        .take(TOP_MOVERS)
+                        .map(|motion| {
+                            let name = player_name(&config, &motion.player);
+                            format!("{} {}", name, motion.milli_silver / 1000)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    // only tweet the biggest whale
+                    if let Some(whale) = motions.first() {
+                        if whale.milli_silver > share.state.most_millisilver_in_motion {
+                            let player =
+                                describe_player(&mut share.state, &config, &whale.player, block)
+                                    .await;
 
-                        let mut whale_tweets: Vec<String> = vec![];
-                        if arrival.milliSilverMoved > share.state.most_millisilver_in_motion {
                             let tweet = format!(
-                                "Sophon 06cfe9ac TX: Whale alert {} silver in motion via {} #darkforest",
-                                arrival.milliSilverMoved / 1000,
-                                arrival.player.id,
+                                "Sophon 06cfe9ac TX: Whale alert {} silver in motion across {} moves via {}. Top movers: {} #darkforest",
+                                whale.milli_silver / 1000,
+                                whale.moves,
+                                player,
+                                top,
                             );
-                            whale_tweets.push(tweet);
+                            share.state.tweets.push_back(Post::new(tweet, block));
 
-                            share.state.most_millisilver_in_motion = arrival.milliSilverMoved;
+                            share.state.most_millisilver_in_motion = whale.milli_silver;
                             dirty = true;
                         }
+                    }
 
-                        // only tweet the biggest whale
-                        if let Some(tweet) = whale_tweets.last() {
-                            share.state.tweets.push_back(tweet.to_string());
+                    let energy_whale = motions.iter().max_by_key(|motion| motion.milli_energy);
+                    if let Some(whale) = energy_whale {
+                        if whale.milli_energy > share.state.most_millienergy_in_motion {
+                            let player =
+                                describe_player(&mut share.state, &config, &whale.player, block)
+                                    .await;
+
+                            let tweet = format!(
+                                "Sophon 38d1f5a7 TX: Armada alert {} energy in motion across {} moves via {} #darkforest",
+                                whale.milli_energy / 1000,
+                                whale.moves,
+                                player,
+                            );
+                            share.state.tweets.push_back(Post::new(tweet, block));
+
+                            share.state.most_millienergy_in_motion = whale.milli_energy;
                             dirty = true;
                         }
                     }
 
                     // write out to disc
                     if dirty {
-                        if let Ok(state_json) = serde_json::to_string(&share.state) {
-                            let _ = std::fs::write(STATE_FILE, state_json);
-                        }
+                        save_state(&share.state);
                         dirty = false;
                     }
                 }
@@ -189,15 +647,31 @@ async fn collect_from_graph(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<()
     }
 }
 
-async fn collect_from_node(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<(), SophonError> {
+async fn collect_from_node(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
     let mut dirty = false;
 
     loop {
+        rpc.health_check().await;
+
         // scope for mutex release
         {
             let mut share = wrapped_state.lock().await;
 
-            if let Ok(significant_radius) = df_radius().await {
+            // pin the cycle to the subgraph's latest block so node and graph posts agree
+            let block = match pin_block(&mut share.state, &rpc, &config).await {
+                Some(block) => block,
+                None => {
+                    drop(share);
+                    sleep(COLLECT_DELAY).await;
+                    continue;
+                }
+            };
+
+            if let Ok(significant_radius) = df_radius(&rpc, block).await {
                 dbg!(significant_radius);
 
                 let significant = (significant_radius / 1000) * 1000;
@@ -208,14 +682,14 @@ async fn collect_from_node(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<(),
                         significant_radius
                     );
 
-                    share.state.tweets.push_back(tweet);
+                    share.state.tweets.push_back(Post::new(tweet, block));
 
                     share.state.significant_radius = significant;
                     dirty = true;
                 }
             }
 
-            if let Ok(significant_user) = df_players().await {
+            if let Ok(significant_user) = df_players(&rpc, block).await {
                 dbg!(significant_user);
 
                 let significant = (significant_user / 10) * 10;
@@ -226,7 +700,7 @@ async fn collect_from_node(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<(),
                         significant_user
                     );
 
-                    share.state.tweets.push_back(tweet);
+                    share.state.tweets.push_back(Post::new(tweet, block));
 
                     share.state.significant_user = significant;
                     dirty = true;
@@ -234,9 +708,7 @@ async fn collect_from_node(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<(),
             }
 
             if dirty {
-                if let Ok(state_json) = serde_json::to_string(&share.state) {
-                    let _ = std::fs::write(STATE_FILE, state_json);
-                }
+                save_state(&share.state);
                 dirty = false;
             }
         }
@@ -245,47 +717,176 @@ async fn collect_from_node(wrapped_state: Arc<Mutex<SophonShare>>) -> Result<(),
     }
 }
 
-async fn tweet_counts() -> Result<(), SophonError> {
+/// Posts the round specific contract events configured for the current round,
+/// like captures or space junk, as they happen.
+async fn collect_round_events(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
+    let round_events = &config.round().events;
+
+    loop {
+        // scope for mutex release
+        if !round_events.is_empty() {
+            let mut share = wrapped_state.lock().await;
+
+            if let Some(block) = pin_block(&mut share.state, &rpc, &config).await {
+                let mut dirty = false;
+
+                for round_event in round_events {
+                    // the first cycle only posts what happens from now on
+                    let from = match share.state.event_blocks.get(&round_event.event) {
+                        Some(last) => last + 1,
+                        None => block,
+                    };
+
+                    if let Ok(events) =
+                        df_events(&rpc, round_event.event.as_str(), from, block).await
+                    {
+                        for event in events {
+                            let mut tweet = round_event.template.clone();
+                            for param in event.params {
+                                let value = match param.value {
+                                    ethabi::Token::Address(address) => {
+                                        player_name(&config, format!("{:?}", address).as_str())
+                                    }
+                                    ethabi::Token::Uint(uint) => uint.to_string(),
+                                    token => token.to_string(),
+                                };
+                                tweet = tweet.replace(
+                                    format!("{{{}}}", param.name).as_str(),
+                                    value.as_str(),
+                                );
+                            }
+
+                            share.state.tweets.push_back(Post::new(tweet, event.block));
+                        }
+
+                        share
+                            .state
+                            .event_blocks
+                            .insert(round_event.event.clone(), block);
+                        dirty = true;
+                    }
+                }
+
+                if dirty {
+                    save_state(&share.state);
+                }
+            }
+        }
+
+        sleep(COLLECT_DELAY).await;
+    }
+}
+
+async fn tweet_counts(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
     loop {
         sleep(COUNTS_DELAY).await;
 
-        if let Ok(counts) = df_counts().await {
+        let block = {
+            let mut share = wrapped_state.lock().await;
+            pin_block(&mut share.state, &rpc, &config).await
+        };
+
+        let block = match block {
+            Some(block) => block,
+            None => continue,
+        };
+
+        if let Ok(counts) = df_counts(&rpc, block).await {
             dbg!(counts.clone());
 
+            let totals = counts
+                .iter()
+                .enumerate()
+                .map(|(level, count)| format!("lvl{}:{}", level, count))
+                .collect::<Vec<_>>()
+                .join(", ");
+
             let tweet = format!(
-                "Sophon 02369284 TX: Universe planet totals: lvl0:{}, lvl1:{}, lvl2:{}, lvl3:{}, lvl4:{}, lvl5:{}, lvl6:{}, lvl7:{} #darkforest",
-                counts[0], counts[1], counts[2], counts[3], counts[4], counts[5], counts[6], counts[7]
+                "Sophon 02369284 TX: Universe planet totals: {} #darkforest",
+                totals
             );
 
-            let _ = send(tweet).await;
+            if send(tweet).await.is_ok() {
+                println!("sophon: posted data from block {}", block);
+            }
         }
     }
 }
 
+/// Writes `state` to the state file, a failed write is caught up by the next save.
+pub fn save_state(state: &SophonState) {
+    if let Ok(state_json) = serde_json::to_string(state) {
+        let _ = std::fs::write(STATE_FILE, state_json);
+    }
+}
+
 pub struct SophonShare {
     state: SophonState,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
 pub struct SophonState {
     /// count of unprocessed arrivalsQueues
     most_arrivals_in_motion: usize,
     /// n hundred thousandth arrival
     significant_arrival: u32,
-    /// longest move in distance
-    longest_move: u32,
-    /// Whale alert in millisilver
-    most_millisilver_in_motion: u32,
+    /// longest move in game units
+    longest_distance: u64,
+    /// longest move in seconds
+    longest_duration: u32,
+    /// most millisilver one player has had in motion
+    most_millisilver_in_motion: u64,
+    /// most millienergy one player has had in motion
+    most_millienergy_in_motion: u64,
     /// last significant user count
     significant_user: u32,
-    /// biggest hat
-    hat_level: u32,
-    /// artifact planet level (rarity)
-    planet_level: u32,
+    /// last hat posted
+    hat_cursor: Option<Cursor>,
+    /// last artifact posted
+    artifact_cursor: Option<Cursor>,
     /// last significant radius
     significant_radius: u64,
     /// scheduled tweets
-    tweets: VecDeque<String>,
+    tweets: VecDeque<Post>,
+    /// last block we pinned a cycle to, checked for reorgs on the next one
+    last_block: Option<Block>,
+    /// subgraph is too far behind chain head to post from
+    graph_lagging: bool,
+    /// last block scanned for each round event
+    event_blocks: HashMap<String, u64>,
+    /// cached player profiles by address
+    profiles: HashMap<String, PlayerProfile>,
+    /// artifact tracker progress
+    artifacts: ArtifactState,
+    /// best of the day and week so far
+    rolling: RollingRecords,
+    /// when each activity series was last flagged as unusual
+    anomalies: AnomalyState,
+    /// snapshots and running totals for digests
+    digest: DigestState,
+    /// who attacks and allies with whom
+    relations: RelationState,
+    /// planet owners and home worlds watched for captures
+    captures: CaptureState,
+    /// planet upgrade ranks and branch totals
+    upgrades: UpgradeState,
+    /// game constants and admin state by name, as last read
+    params: BTreeMap<String, String>,
+    /// in flight arrivals already warned about
+    warned_arrivals: HashSet<String>,
+    /// round this state follows once Sophon has moved past the configured one
+    round: Option<String>,
+    /// where the round is in its life
+    lifecycle: LifecycleState,
 }
 
 #[derive(Debug)]
@@ -298,3 +899,15 @@ impl From<std::io::Error> for SophonError {
         SophonError::Internal
     }
 }
+
+impl From<NodeError> for SophonError {
+    fn from(_err: NodeError) -> Self {
+        SophonError::Internal
+    }
+}
+
+impl From<GraphError> for SophonError {
+    fn from(_err: GraphError) -> Self {
+        SophonError::Internal
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use crate::config::SophonConfig;
+
+/// How posts name a player: their registered ENS name or twitter handle from
+/// `names_file`, otherwise a shortened address. The file is a json object of
+/// address to name and is reread every time, so it can be edited while running.
+pub fn player_name(config: &SophonConfig, id: &str) -> String {
+    let names = std::fs::read_to_string(config.names_file.as_str())
+        .ok()
+        .and_then(|names_json| serde_json::from_str::<HashMap<String, String>>(&names_json).ok())
+        .unwrap_or_default();
+
+    let id = id.to_lowercase();
+    match names
+        .iter()
+        .find(|(address, _)| address.to_lowercase() == id)
+    {
+        Some((_, name)) => name.clone(),
+        None => short_address(id.as_str()),
+    }
+}
+
+/// ie `0xabcd…1234`
+pub fn short_address(id: &str) -> String {
+    if id.len() <= 12 {
+        return id.to_string();
+    }
+
+    format!("{}…{}", &id[..6], &id[id.len() - 4..])
+}
+
+/// A planet's name as the Dark Forest client shows it, from the client's word
+/// list in `planet_words_file`. Without the list, a shortened location id.
+pub fn planet_name(config: &SophonConfig, location_id: &str) -> String {
+    let words = std::fs::read_to_string(config.planet_words_file.as_str())
+        .ok()
+        .and_then(|words_json| serde_json::from_str::<Vec<String>>(&words_json).ok())
+        .unwrap_or_default();
+
+    // the client's ids are always 64 characters
+    let id = format!("{:0>64}", location_id.trim_start_matches("0x"));
+    if words.is_empty() {
+        return short_address(id.trim_start_matches('0'));
+    }
+
+    procedural_name(&words, &id)
+}
+
+/// The client's `getPlanetNameHash`: a sin based generator seeded from the
+/// id, skipping its first two bytes, picks two words or, rarely, a joke.
+fn procedural_name(words: &[String], id: &str) -> String {
+    let offset = u64::from_str_radix(id.get(4..14).unwrap_or_default(), 16).unwrap_or_default();
+
+    let mut count = 0;
+    let mut random_int = || {
+        count += 1;
+        let x = ((count + offset) as f64).sin() * 10000.0;
+        ((x - x.floor()) * (1 << 24) as f64).floor() as usize
+    };
+
+    if random_int() % 1024 == 0 {
+        return "Clown Town".to_string();
+    }
+
+    let mut name = vec![];
+    for _ in 0..2 {
+        let word = &words[random_int() % words.len()];
+        let mut chars = word.chars();
+        let capitalized = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+            None => String::new(),
+        };
+        name.push(capitalized);
+    }
+
+    name.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // expected names are the client's steps run under node, so they share
+    // the browser's floating point
+    const WORDS: &[&str] = &["alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta"];
+
+    fn name(id: &str) -> String {
+        let words = WORDS
+            .iter()
+            .map(|word| word.to_string())
+            .collect::<Vec<_>>();
+        procedural_name(&words, id)
+    }
+
+    #[test]
+    fn names_match_the_client() {
+        assert_eq!(
+            name("0000a3f9c2e0b7d11c4a0b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f6"),
+            "Gamma Zeta"
+        );
+        assert_eq!(
+            name("00000000ffe1c2a9d0b3e4f5a6b7c8d9e0f1a2b3c4d5e6f708192a3b4c5d6e7f"),
+            "Gamma Gamma"
+        );
+        assert_eq!(
+            name("000059e7b1d3a4c5f6e7d8c9b0a1f2e3d4c5b6a7980716253443526170819203"),
+            "Beta Epsilon"
+        );
+        assert_eq!(
+            name("000000000003c000000000000000000000000000000000000000000000000000"),
+            "Clown Town"
+        );
+    }
+
+    #[test]
+    fn without_words_ids_are_shortened() {
+        let config = SophonConfig {
+            planet_words_file: String::new(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            planet_name(
+                &config,
+                "0000a3f9c2e0b7d11c4a0b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f6"
+            ),
+            "a3f9c2…e5f6"
+        );
+    }
+}
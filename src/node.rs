@@ -1,136 +1,845 @@
+use ethabi::Token;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tokio_compat_02::FutureExt;
 use web3::contract::{Contract, Options};
-use web3::types::U256;
-
-pub async fn df_radius() -> Result<u64, NodeError> {
-    let http = web3::transports::Http::new("https://rpc.xdaichain.com")?;
-    let web3 = web3::Web3::new(http);
-
-    let contract = Contract::from_json(
-        web3.eth(),
-        "678ACb78948Be7F354B28DaAb79B1ABD81574c1B".parse()?,
-        // todo would be nice to grab the .abi directly from DarkForestCore.json
-        include_bytes!("../DarkForest.abi"),
-    )?;
-
-    let result = contract.query("worldRadius", (), None, Options::default(), None);
-    let world_radius: U256 = result.compat().await?;
-    let world_radius: u64 = world_radius.as_u64();
-
-    Ok(world_radius)
-}
-
-pub async fn df_players() -> Result<u32, NodeError> {
-    let http = web3::transports::Http::new("https://rpc.xdaichain.com")?;
-    let web3 = web3::Web3::new(http);
-
-    let contract = Contract::from_json(
-        web3.eth(),
-        "678ACb78948Be7F354B28DaAb79B1ABD81574c1B".parse()?,
-        // todo would be nice to grab the .abi directly from DarkForestCore.json
-        include_bytes!("../DarkForest.abi"),
-    )?;
-
-    let result = contract.query("getNPlayers", (), None, Options::default(), None);
-    let n_players: U256 = result.compat().await?;
-    let n_players: u32 = n_players.as_u32();
-
-    Ok(n_players)
-}
-
-pub async fn df_counts() -> Result<Vec<u64>, NodeError> {
-    let http = web3::transports::Http::new("https://rpc.xdaichain.com")?;
-    let web3 = web3::Web3::new(http);
-
-    let contract = Contract::from_json(
-        web3.eth(),
-        "678ACb78948Be7F354B28DaAb79B1ABD81574c1B".parse()?,
-        // todo would be nice to grab the .abi directly from DarkForestCore.json
-        include_bytes!("../DarkForest.abi"),
-    )?;
-
-    let mut res = vec![];
-    let result = contract.query(
-        "initializedPlanetCountByLevel",
-        (0_u32,),
-        None,
-        Options::default(),
-        None,
-    );
-    let zero: U256 = result.compat().await?;
-    res.push(zero.low_u64());
-
-    let result = contract.query(
-        "initializedPlanetCountByLevel",
-        (1_u32,),
-        None,
-        Options::default(),
-        None,
-    );
-    let one: U256 = result.compat().await?;
-    res.push(one.low_u64());
-
-    let result = contract.query(
-        "initializedPlanetCountByLevel",
-        (2_u32,),
-        None,
-        Options::default(),
-        None,
-    );
-    let two: U256 = result.compat().await?;
-    res.push(two.low_u64());
-
-    let result = contract.query(
-        "initializedPlanetCountByLevel",
-        (3_u32,),
-        None,
-        Options::default(),
-        None,
-    );
-    let three: U256 = result.compat().await?;
-    res.push(three.low_u64());
-
-    let result = contract.query(
-        "initializedPlanetCountByLevel",
-        (4_u32,),
-        None,
-        Options::default(),
-        None,
-    );
-    let four: U256 = result.compat().await?;
-    res.push(four.low_u64());
-
-    let result = contract.query(
-        "initializedPlanetCountByLevel",
-        (5_u32,),
-        None,
-        Options::default(),
-        None,
-    );
-    let five: U256 = result.compat().await?;
-    res.push(five.low_u64());
-
-    let result = contract.query(
-        "initializedPlanetCountByLevel",
-        (6_u32,),
-        None,
-        Options::default(),
-        None,
-    );
-    let six: U256 = result.compat().await?;
-    res.push(six.low_u64());
-
-    let result = contract.query(
-        "initializedPlanetCountByLevel",
-        (7_u32,),
-        None,
-        Options::default(),
-        None,
-    );
-    let seven: U256 = result.compat().await?;
-    res.push(seven.low_u64());
-
-    Ok(res)
+use web3::transports::{Batch, Either, Http, WebSocket};
+use web3::types::{Address, BlockId, BlockNumber, Bytes, CallRequest, FilterBuilder, U256, U64};
+
+use crate::config::SophonConfig;
+
+/// rules and admin state watched for changes, all read in one batch
+const GAME_PARAMS: &[&str] = &[
+    "PERLIN_THRESHOLD_1",
+    "PERLIN_THRESHOLD_2",
+    "BIOME_THRESHOLD_1",
+    "BIOME_THRESHOLD_2",
+    "PLANET_RARITY",
+    "SILVER_RARITY_1",
+    "SILVER_RARITY_2",
+    "SILVER_RARITY_3",
+    "TIME_FACTOR_HUNDREDTHS",
+    "ARTIFACT_LOCKUP_DURATION_SECONDS",
+    "getDefaultStats",
+    "getPlanetLevelThresholds",
+    "paused",
+    "adminAddress",
+];
+
+/// The contract functions and events each collector reads, checked against
+/// the round's abi at startup. A collector whose names the abi lacks is left
+/// off rather than failing every cycle.
+const COLLECTOR_ABI: &[(&str, &[&str])] = &[
+    (
+        "follow_round",
+        &[
+            "worldRadius",
+            "getNPlayers",
+            "getPlanetLevelThresholds",
+            "initializedPlanetCountByLevel",
+            "paused",
+            "PlayerInitialized",
+        ],
+    ),
+    ("collect_from_graph", &["TIME_FACTOR_HUNDREDTHS"]),
+    ("collect_from_node", &["worldRadius", "getNPlayers"]),
+    // plus the round's own `events`
+    ("collect_round_events", &[]),
+    (
+        "collect_artifacts",
+        &[
+            "getArtifactById",
+            "bulkGetArtifactsByIds",
+            "getPlayerArtifactIds",
+            "FoundArtifact",
+            "DepositedArtifact",
+            "WithdrewArtifact",
+        ],
+    ),
+    (
+        "collect_digest",
+        &[
+            "worldRadius",
+            "getNPlayers",
+            "getPlanetLevelThresholds",
+            "initializedPlanetCountByLevel",
+        ],
+    ),
+    // captures run in the same cycle
+    (
+        "collect_relations",
+        &[
+            "getArtifactById",
+            "bulkGetArtifactsByIds",
+            "planetsExtendedInfo",
+            "PlayerInitialized",
+            "PlanetTransferred",
+            "FoundArtifact",
+            "DepositedArtifact",
+            "WithdrewArtifact",
+        ],
+    ),
+    (
+        "collect_upgrades",
+        &["planetsExtendedInfo", "getUpgrades", "PlanetUpgraded"],
+    ),
+    ("collect_params", GAME_PARAMS),
+    (
+        "tweet_counts",
+        &["getPlanetLevelThresholds", "initializedPlanetCountByLevel"],
+    ),
+];
+
+/// most blocks asked for in one eth_getLogs, public endpoints refuse much more
+const LOG_RANGE: u64 = 10_000;
+
+/// Multicall `aggregate`, the only function we need from it
+static MULTICALL_ABI: &[u8] = br#"[{"type":"function","name":"aggregate","stateMutability":"view","inputs":[{"name":"calls","type":"tuple[]","components":[{"name":"target","type":"address"},{"name":"callData","type":"bytes"}]}],"outputs":[{"name":"blockNumber","type":"uint256"},{"name":"returnData","type":"bytes[]"}]}]"#;
+
+/// how long an endpoint sits out after failing before we try it again
+const UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(60 * 5);
+
+type DfTransport = Either<WebSocket, Http>;
+
+/// The set of json-rpc endpoints Sophon reads from. Reads go to the healthiest
+/// endpoint first and fail over down the list, optionally requiring several
+/// endpoints to agree before a value is trusted.
+pub struct Rpc {
+    endpoints: Mutex<Vec<Endpoint>>,
+    quorum: usize,
+    multicall: Option<Address>,
+    /// the followed round's DarkForestCore
+    contract_address: Address,
+    abi: ethabi::Contract,
+    /// collectors left off because the abi lacks something they read
+    disabled: Vec<&'static str>,
+}
+
+struct Endpoint {
+    url: String,
+    /// consecutive failures
+    failures: u32,
+    /// skip until this instant unless every endpoint is unhealthy
+    unhealthy_until: Option<Instant>,
+}
+
+impl Rpc {
+    pub fn new(config: &SophonConfig) -> Result<Self, NodeError> {
+        let endpoints = config
+            .rpc_endpoints
+            .iter()
+            .map(|url| Endpoint {
+                url: url.clone(),
+                failures: 0,
+                unhealthy_until: None,
+            })
+            .collect::<Vec<_>>();
+
+        let multicall = match &config.multicall_address {
+            Some(address) => Some(address.trim_start_matches("0x").parse()?),
+            None => None,
+        };
+
+        let round = config.round();
+        let abi = match &round.abi_path {
+            Some(abi_path) => load_abi(abi_path.as_str())?,
+            None => ethabi::Contract::load(&include_bytes!("../DarkForest.abi")[..])?,
+        };
+
+        let mut disabled = vec![];
+        for (collector, names) in COLLECTOR_ABI {
+            let mut names = names.to_vec();
+            if *collector == "collect_round_events" {
+                names.extend(round.events.iter().map(|e| e.event.as_str()));
+            }
+
+            let missing = missing_from_abi(&abi, &names);
+            if !missing.is_empty() {
+                eprintln!(
+                    "sophon: abi is missing {}, leaving {} off",
+                    missing.join(", "),
+                    collector
+                );
+                disabled.push(*collector);
+            }
+        }
+
+        Ok(Rpc {
+            quorum: config.rpc_quorum.max(1).min(endpoints.len().max(1)),
+            endpoints: Mutex::new(endpoints),
+            multicall,
+            contract_address: round.contract_address.trim_start_matches("0x").parse()?,
+            abi,
+            disabled,
+        })
+    }
+
+    /// Whether the round's abi has everything `collector` reads.
+    pub fn enabled(&self, collector: &str) -> bool {
+        !self.disabled.contains(&collector)
+    }
+
+    /// Pings every endpoint so a recovered provider is put back in rotation
+    /// and a dead one is benched before a read has to find out the hard way.
+    pub async fn health_check(&self) {
+        for url in self.urls() {
+            let healthy = match connect(url.as_str()).await {
+                Ok(web3) => web3.eth().block_number().compat().await.is_ok(),
+                Err(_) => false,
+            };
+            self.report(url.as_str(), healthy);
+        }
+    }
+
+    /// every endpoint, healthy ones first, each group in configured order
+    fn urls(&self) -> Vec<String> {
+        let now = Instant::now();
+        let endpoints = self.endpoints.lock().unwrap();
+
+        let (healthy, unhealthy): (Vec<_>, Vec<_>) =
+            endpoints.iter().partition(|e| match e.unhealthy_until {
+                Some(until) => until <= now,
+                None => true,
+            });
+
+        healthy
+            .into_iter()
+            .chain(unhealthy)
+            .map(|e| e.url.clone())
+            .collect()
+    }
+
+    fn report(&self, url: &str, healthy: bool) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        if let Some(endpoint) = endpoints.iter_mut().find(|e| e.url == url) {
+            if healthy {
+                endpoint.failures = 0;
+                endpoint.unhealthy_until = None;
+            } else {
+                endpoint.failures += 1;
+                endpoint.unhealthy_until = Some(Instant::now() + UNHEALTHY_COOLDOWN);
+                eprintln!(
+                    "sophon: rpc {} failed {} times in a row",
+                    endpoint.url, endpoint.failures
+                );
+            }
+        }
+    }
+
+    /// Runs `read` against endpoints in health order until the configured
+    /// quorum of them return the same value.
+    async fn read<R, F, Fut>(&self, read: F) -> Result<R, NodeError>
+    where
+        R: PartialEq,
+        F: Fn(Connection) -> Fut,
+        Fut: Future<Output = Result<R, NodeError>>,
+    {
+        self.read_quorum(self.quorum, read).await
+    }
+
+    /// Runs `read` against endpoints in health order until `quorum` of them
+    /// return the same value. A quorum of 1 is plain failover.
+    async fn read_quorum<R, F, Fut>(&self, quorum: usize, read: F) -> Result<R, NodeError>
+    where
+        R: PartialEq,
+        F: Fn(Connection) -> Fut,
+        Fut: Future<Output = Result<R, NodeError>>,
+    {
+        let mut answers: Vec<(R, usize)> = vec![];
+        let mut last_err = NodeError::RPCUrl;
+
+        for url in self.urls() {
+            let res = match Connection::new(url.as_str(), self).await {
+                Ok(connection) => read(connection).await,
+                Err(err) => Err(err),
+            };
+
+            match res {
+                Ok(value) => {
+                    self.report(url.as_str(), true);
+
+                    let votes = match answers.iter_mut().find(|(answer, _)| *answer == value) {
+                        Some((_, votes)) => {
+                            *votes += 1;
+                            *votes
+                        }
+                        None => {
+                            answers.push((value, 1));
+                            1
+                        }
+                    };
+
+                    if votes >= quorum {
+                        let idx = answers.iter().position(|(_, v)| *v == votes).unwrap();
+                        return Ok(answers.swap_remove(idx).0);
+                    }
+                }
+                Err(err) => {
+                    self.report(url.as_str(), false);
+                    last_err = err;
+                }
+            }
+        }
+
+        if answers.is_empty() {
+            Err(last_err)
+        } else {
+            Err(NodeError::NoQuorum)
+        }
+    }
+}
+
+/// Reads a contract abi from `path`, either a bare abi array or a Hardhat or
+/// Truffle artifact like DarkForestCore.json with the abi under an `abi` key.
+fn load_abi(path: &str) -> Result<ethabi::Contract, NodeError> {
+    let json = std::fs::read_to_string(path).map_err(|_| NodeError::ContractAbi)?;
+    let json = serde_json::from_str::<serde_json::Value>(json.as_str())?;
+
+    let abi = match json {
+        serde_json::Value::Object(mut artifact) => {
+            artifact.remove("abi").ok_or(NodeError::ContractAbi)?
+        }
+        abi => abi,
+    };
+
+    Ok(ethabi::Contract::load(
+        serde_json::to_vec(&abi)?.as_slice(),
+    )?)
+}
+
+/// Every one of `names` that `abi` has neither as a function nor as an event.
+fn missing_from_abi(abi: &ethabi::Contract, names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .filter(|name| abi.function(name).is_err() && abi.event(name).is_err())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+async fn connect(url: &str) -> Result<web3::Web3<DfTransport>, NodeError> {
+    let transport = if url.starts_with("ws://") || url.starts_with("wss://") {
+        Either::Left(WebSocket::new(url).compat().await?)
+    } else {
+        Either::Right(Http::new(url)?)
+    };
+
+    Ok(web3::Web3::new(transport))
+}
+
+/// Pins a contract read to a block so everything in a collection cycle agrees
+fn at(block: u64) -> Option<BlockId> {
+    Some(BlockId::Number(BlockNumber::Number(U64::from(block))))
+}
+
+/// A connection to one endpoint with the Dark Forest contract bound to it.
+struct Connection {
+    web3: web3::Web3<DfTransport>,
+    contract: Contract<DfTransport>,
+    multicall: Option<Address>,
+}
+
+impl Connection {
+    async fn new(url: &str, rpc: &Rpc) -> Result<Self, NodeError> {
+        let web3 = connect(url).await?;
+        let contract = Contract::new(web3.eth(), rpc.contract_address, rpc.abi.clone());
+
+        Ok(Connection {
+            web3,
+            contract,
+            multicall: rpc.multicall,
+        })
+    }
+
+    /// Calls several contract view functions at `block` in one round trip. Goes
+    /// through the multicall contract when one is configured, otherwise sends a
+    /// json-rpc batch.
+    async fn batch(
+        &self,
+        calls: &[(&str, Vec<Token>)],
+        block: u64,
+    ) -> Result<Vec<Vec<Token>>, NodeError> {
+        let abi = self.contract.abi();
+
+        let mut encoded = vec![];
+        for (name, params) in calls {
+            encoded.push(abi.function(name)?.encode_input(params)?);
+        }
+
+        let outputs = match self.multicall {
+            Some(multicall) => self.multicall(multicall, encoded, block).await?,
+            None => self.json_rpc_batch(encoded, block).await?,
+        };
+
+        let mut res = vec![];
+        for ((name, _), output) in calls.iter().zip(outputs) {
+            res.push(abi.function(name)?.decode_output(&output)?);
+        }
+
+        Ok(res)
+    }
+
+    async fn json_rpc_batch(
+        &self,
+        encoded: Vec<Vec<u8>>,
+        block: u64,
+    ) -> Result<Vec<Vec<u8>>, NodeError> {
+        let block = at(block);
+
+        let batch = Batch::new(self.web3.transport().clone());
+        let eth = web3::Web3::new(batch.clone()).eth();
+
+        // queued up, nothing goes out until submit_batch
+        let pending = encoded
+            .into_iter()
+            .map(|data| {
+                let req = CallRequest {
+                    to: Some(self.contract.address()),
+                    data: Some(Bytes(data)),
+                    ..Default::default()
+                };
+                eth.call(req, block)
+            })
+            .collect::<Vec<_>>();
+
+        batch.submit_batch().compat().await?;
+
+        let mut res = vec![];
+        for call in pending {
+            res.push(call.compat().await?.0);
+        }
+
+        Ok(res)
+    }
+
+    async fn multicall(
+        &self,
+        multicall: Address,
+        encoded: Vec<Vec<u8>>,
+        block: u64,
+    ) -> Result<Vec<Vec<u8>>, NodeError> {
+        let abi = ethabi::Contract::load(MULTICALL_ABI)?;
+        let aggregate = abi.function("aggregate")?;
+
+        let calls = encoded
+            .into_iter()
+            .map(|data| {
+                Token::Tuple(vec![
+                    Token::Address(self.contract.address()),
+                    Token::Bytes(data),
+                ])
+            })
+            .collect();
+
+        let req = CallRequest {
+            to: Some(multicall),
+            data: Some(Bytes(aggregate.encode_input(&[Token::Array(calls)])?)),
+            ..Default::default()
+        };
+        let output = self.web3.eth().call(req, at(block)).compat().await?;
+
+        // (blockNumber, returnData[])
+        match aggregate.decode_output(&output.0)?.pop() {
+            Some(Token::Array(results)) => Ok(results
+                .into_iter()
+                .filter_map(|result| result.into_bytes())
+                .collect()),
+            _ => Err(NodeError::ContractResponseParse),
+        }
+    }
+}
+
+/// Chain head of whichever endpoint answers first. Providers are rarely on the
+/// exact same block so this never asks for a quorum.
+pub async fn df_head(rpc: &Rpc) -> Result<u64, NodeError> {
+    rpc.read_quorum(1, |node| async move {
+        let head = node.web3.eth().block_number().compat().await?;
+        Ok(head.as_u64())
+    })
+    .await
+}
+
+/// Canonical hash of `block` as 0x prefixed lowercase hex, the way the subgraph reports it.
+pub async fn df_block_hash(rpc: &Rpc, block: u64) -> Result<String, NodeError> {
+    rpc.read(|node| async move {
+        let block = node.web3.eth().block(at(block).unwrap()).compat().await?;
+        match block.and_then(|block| block.hash) {
+            Some(hash) => Ok(format!("{:?}", hash)),
+            None => Err(NodeError::ContractResponseParse),
+        }
+    })
+    .await
+}
+
+/// One contract event, decoded with the round's abi.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DfEvent {
+    pub block: u64,
+    pub params: Vec<ethabi::LogParam>,
+}
+
+/// Every `event` the contract emitted from `from` to `to` inclusive, oldest first.
+pub async fn df_events(
+    rpc: &Rpc,
+    event: &str,
+    from: u64,
+    to: u64,
+) -> Result<Vec<DfEvent>, NodeError> {
+    rpc.read(|node| async move {
+        let event = node.contract.abi().event(event)?;
+
+        let mut res = vec![];
+        let mut start = from;
+        while start <= to {
+            let end = std::cmp::min(start + LOG_RANGE - 1, to);
+
+            let filter = FilterBuilder::default()
+                .address(vec![node.contract.address()])
+                .topics(Some(vec![event.signature()]), None, None, None)
+                .from_block(BlockNumber::Number(U64::from(start)))
+                .to_block(BlockNumber::Number(U64::from(end)))
+                .build();
+
+            for log in node.web3.eth().logs(filter).compat().await? {
+                let block = log.block_number.map(|block| block.as_u64()).unwrap_or(end);
+                let log = event.parse_log(ethabi::RawLog {
+                    topics: log.topics,
+                    data: log.data.0,
+                })?;
+
+                res.push(DfEvent {
+                    block,
+                    params: log.params,
+                });
+            }
+
+            start = end + 1;
+        }
+
+        Ok(res)
+    })
+    .await
+}
+
+pub async fn df_radius(rpc: &Rpc, block: u64) -> Result<u64, NodeError> {
+    rpc.read(|node| async move {
+        let result = node
+            .contract
+            .query("worldRadius", (), None, Options::default(), at(block));
+        let world_radius: U256 = result.compat().await?;
+        Ok(world_radius.as_u64())
+    })
+    .await
+}
+
+/// How much faster than the base game the round runs, in hundredths.
+pub async fn df_time_factor(rpc: &Rpc, block: u64) -> Result<u64, NodeError> {
+    rpc.read(|node| async move {
+        let result = node.contract.query(
+            "TIME_FACTOR_HUNDREDTHS",
+            (),
+            None,
+            Options::default(),
+            at(block),
+        );
+        let time_factor: U256 = result.compat().await?;
+        Ok(time_factor.as_u64())
+    })
+    .await
+}
+
+pub async fn df_players(rpc: &Rpc, block: u64) -> Result<u32, NodeError> {
+    rpc.read(|node| async move {
+        let result = node
+            .contract
+            .query("getNPlayers", (), None, Options::default(), at(block));
+        let n_players: U256 = result.compat().await?;
+        Ok(n_players.as_u32())
+    })
+    .await
+}
+
+/// Whether the admin has paused the game at `block`.
+pub async fn df_paused(rpc: &Rpc, block: u64) -> Result<bool, NodeError> {
+    rpc.read(|node| async move {
+        let result = node
+            .contract
+            .query("paused", (), None, Options::default(), at(block));
+        let paused: bool = result.compat().await?;
+        Ok(paused)
+    })
+    .await
+}
+
+/// Initialized planet count for every level the contract knows about at
+/// `block`, with all levels read in one batch.
+pub async fn df_counts(rpc: &Rpc, block: u64) -> Result<Vec<u64>, NodeError> {
+    rpc.read(|node| async move {
+        let result = node.contract.query(
+            "getPlanetLevelThresholds",
+            (),
+            None,
+            Options::default(),
+            at(block),
+        );
+        let thresholds: Vec<U256> = result.compat().await?;
+
+        let calls = (0..thresholds.len())
+            .map(|level| {
+                (
+                    "initializedPlanetCountByLevel",
+                    vec![Token::Uint(U256::from(level))],
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut res = vec![];
+        for mut output in node.batch(&calls, block).await? {
+            match output.pop().and_then(Token::into_uint) {
+                Some(count) => res.push(count.low_u64()),
+                None => return Err(NodeError::ContractResponseParse),
+            }
+        }
+
+        Ok(res)
+    })
+    .await
+}
+
+/// Every GAME_PARAMS value at `block` by name, formatted for posting. Arrays
+/// and structs are split into one value per element, ie `getDefaultStats[2][4]`.
+pub async fn df_params(rpc: &Rpc, block: u64) -> Result<BTreeMap<String, String>, NodeError> {
+    rpc.read(|node| async move {
+        let calls = GAME_PARAMS
+            .iter()
+            .map(|name| (*name, vec![]))
+            .collect::<Vec<_>>();
+
+        let mut res = BTreeMap::new();
+        for (name, output) in GAME_PARAMS.iter().zip(node.batch(&calls, block).await?) {
+            match output.as_slice() {
+                [token] => describe_token(name.to_string(), token, &mut res),
+                _ => describe_token(name.to_string(), &Token::Tuple(output), &mut res),
+            }
+        }
+
+        Ok(res)
+    })
+    .await
+}
+
+/// decimal numbers and lowercase addresses, where ethabi would print hex
+fn describe_token(name: String, token: &Token, res: &mut BTreeMap<String, String>) {
+    match token {
+        Token::Uint(uint) | Token::Int(uint) => {
+            res.insert(name, uint.to_string());
+        }
+        Token::Address(address) => {
+            res.insert(name, format!("{:?}", address));
+        }
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            for (i, token) in tokens.iter().enumerate() {
+                describe_token(format!("{}[{}]", name, i), token, res);
+            }
+        }
+        token => {
+            res.insert(name, token.to_string());
+        }
+    }
+}
+
+/// An artifact as the contract reports it, `ArtifactWithMetadata` minus the upgrade.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DfArtifact {
+    pub id: U256,
+    pub planet_discovered_on: U256,
+    pub planet_level: u64,
+    pub planet_biome: u8,
+    pub minted_at: u64,
+    pub discoverer: Address,
+    pub artifact_type: u8,
+    /// token owner, the contract itself while the artifact is deposited on a planet
+    pub owner: Address,
+    /// planet the artifact is deposited on, zero when it isn't
+    pub location_id: U256,
+}
+
+/// the fields of a tuple token, ethabi 13 has no `into_tuple`
+fn into_tuple(token: Token) -> Option<Vec<Token>> {
+    match token {
+        Token::Tuple(tokens) => Some(tokens),
+        _ => None,
+    }
+}
+
+impl DfArtifact {
+    fn from_token(token: Token) -> Option<Self> {
+        // (artifact, upgrade, owner, locationId)
+        let mut with_metadata = into_tuple(token)?.into_iter();
+        let mut artifact = into_tuple(with_metadata.next()?)?.into_iter();
+        let _upgrade = with_metadata.next()?;
+        let owner = with_metadata.next()?.into_address()?;
+        let location_id = with_metadata.next()?.into_uint()?;
+
+        Some(DfArtifact {
+            id: artifact.next()?.into_uint()?,
+            planet_discovered_on: artifact.next()?.into_uint()?,
+            planet_level: artifact.next()?.into_uint()?.low_u64(),
+            planet_biome: artifact.next()?.into_uint()?.low_u32() as u8,
+            minted_at: artifact.next()?.into_uint()?.low_u64(),
+            discoverer: artifact.next()?.into_address()?,
+            artifact_type: artifact.next()?.into_uint()?.low_u32() as u8,
+            owner,
+            location_id,
+        })
+    }
+}
+
+pub async fn df_artifact(rpc: &Rpc, id: U256, block: u64) -> Result<DfArtifact, NodeError> {
+    rpc.read(|node| async move {
+        let result = node.contract.query(
+            "getArtifactById",
+            (id,),
+            None,
+            Options::default(),
+            at(block),
+        );
+        let artifact: Token = result.compat().await?;
+
+        DfArtifact::from_token(artifact).ok_or(NodeError::ContractResponseParse)
+    })
+    .await
+}
+
+pub async fn df_artifacts(
+    rpc: &Rpc,
+    ids: Vec<U256>,
+    block: u64,
+) -> Result<Vec<DfArtifact>, NodeError> {
+    rpc.read(|node| {
+        let ids = ids.clone();
+        async move {
+            let result = node.contract.query(
+                "bulkGetArtifactsByIds",
+                (ids,),
+                None,
+                Options::default(),
+                at(block),
+            );
+            let artifacts: Token = result.compat().await?;
+
+            artifacts
+                .into_array()
+                .ok_or(NodeError::ContractResponseParse)?
+                .into_iter()
+                .map(|artifact| {
+                    DfArtifact::from_token(artifact).ok_or(NodeError::ContractResponseParse)
+                })
+                .collect()
+        }
+    })
+    .await
+}
+
+/// ids of every artifact `player` holds in their wallet
+pub async fn df_player_artifact_ids(
+    rpc: &Rpc,
+    player: Address,
+    block: u64,
+) -> Result<Vec<U256>, NodeError> {
+    rpc.read(|node| async move {
+        let result = node.contract.query(
+            "getPlayerArtifactIds",
+            (player,),
+            None,
+            Options::default(),
+            at(block),
+        );
+        let ids: Vec<U256> = result.compat().await?;
+        Ok(ids)
+    })
+    .await
+}
+
+/// The parts of `planetsExtendedInfo` Sophon uses.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DfPlanetInfo {
+    /// ranks in the defense, range and speed branches
+    pub upgrade_state: [u64; 3],
+    pub hat_level: u64,
+    /// artifact deposited on the planet
+    pub held_artifact: Option<U256>,
+}
+
+pub async fn df_planet_info(
+    rpc: &Rpc,
+    location: U256,
+    block: u64,
+) -> Result<DfPlanetInfo, NodeError> {
+    rpc.read(|node| async move {
+        let result = node.contract.query(
+            "planetsExtendedInfo",
+            (location,),
+            None,
+            Options::default(),
+            at(block),
+        );
+        #[allow(clippy::type_complexity)]
+        let info: (
+            bool,
+            U256,
+            U256,
+            U256,
+            Token,
+            U256,
+            U256,
+            U256,
+            U256,
+            bool,
+            U256,
+            U256,
+        ) = result.compat().await?;
+
+        Ok(DfPlanetInfo {
+            upgrade_state: [info.5.low_u64(), info.6.low_u64(), info.7.low_u64()],
+            hat_level: info.8.low_u64(),
+            held_artifact: if info.10.is_zero() {
+                None
+            } else {
+                Some(info.10)
+            },
+        })
+    })
+    .await
+}
+
+/// How many ranks each upgrade branch has.
+pub async fn df_upgrade_ranks(rpc: &Rpc, block: u64) -> Result<Vec<usize>, NodeError> {
+    rpc.read(|node| async move {
+        let result = node
+            .contract
+            .query("getUpgrades", (), None, Options::default(), at(block));
+        let upgrades: Token = result.compat().await?;
+
+        upgrades
+            .into_fixed_array()
+            .ok_or(NodeError::ContractResponseParse)?
+            .into_iter()
+            .map(|branch| {
+                branch
+                    .into_fixed_array()
+                    .map(|ranks| ranks.len())
+                    .ok_or(NodeError::ContractResponseParse)
+            })
+            .collect()
+    })
+    .await
+}
+
+/// The contract's own address, what owns an artifact while it's deposited.
+pub fn df_address(rpc: &Rpc) -> Address {
+    rpc.contract_address
 }
 
 #[derive(Debug)]
@@ -140,6 +849,7 @@ pub enum NodeError {
     RPCUrl,
     ContractAbi,
     ContractResponseParse,
+    NoQuorum,
     JsonError,
     HttpError,
 }
@@ -185,3 +895,44 @@ impl From<serde_json::Error> for NodeError {
         NodeError::JsonError
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ABI: &str = r#"[
+        {"type":"function","name":"worldRadius","stateMutability":"view","inputs":[],"outputs":[{"name":"","type":"uint256"}]},
+        {"type":"event","name":"PlanetTransferred","anonymous":false,"inputs":[{"name":"loc","type":"uint256","indexed":false}]}
+    ]"#;
+
+    /// writes `json` to a file of its own under the temp dir
+    fn abi_file(name: &str, json: &str) -> String {
+        let path = std::env::temp_dir().join(format!("sophon_{}_{}", std::process::id(), name));
+        std::fs::write(&path, json).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn loads_a_bare_abi_and_an_artifact() {
+        let bare = load_abi(&abi_file("bare.abi", ABI)).unwrap();
+        assert!(bare.function("worldRadius").is_ok());
+
+        let artifact = format!(r#"{{"contractName":"DarkForestCore","abi":{}}}"#, ABI);
+        let artifact = load_abi(&abi_file("DarkForestCore.json", &artifact)).unwrap();
+        assert!(artifact.function("worldRadius").is_ok());
+        assert!(artifact.event("PlanetTransferred").is_ok());
+
+        assert!(load_abi(&abi_file("no_abi.json", r#"{"contractName":"x"}"#)).is_err());
+    }
+
+    #[test]
+    fn missing_names_are_listed() {
+        let abi = load_abi(&abi_file("missing.abi", ABI)).unwrap();
+
+        let names = ["worldRadius", "PlanetTransferred", "getNPlayers"];
+        assert_eq!(
+            missing_from_abi(&abi, &names),
+            vec!["getNPlayers".to_string()]
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::alert::alert;
+use crate::config::SophonConfig;
+use crate::cycle::{scan_every_cycle, Scan};
+use crate::names::player_name;
+use crate::node::*;
+use crate::twitter::Post;
+use crate::{SophonError, SophonShare, SophonState};
+
+/// Snapshots the round's rules and admin state at startup and every cycle,
+/// posting and alerting on anything that changed since the last snapshot:
+/// pauses, admin handovers and rule tweaks.
+pub async fn collect_params(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
+    scan_every_cycle::<Params>(wrapped_state, rpc, config).await
+}
+
+/// The rules and admin state as of the last snapshot.
+struct Params(BTreeMap<String, String>);
+
+impl Scan for Params {
+    fn of(state: &SophonState) -> Self {
+        Params(state.params.clone())
+    }
+
+    fn put(self, state: &mut SophonState) {
+        state.params = self.0;
+    }
+
+    async fn scan(
+        &mut self,
+        _state: &SophonState,
+        rpc: &Rpc,
+        config: &SophonConfig,
+        block: u64,
+    ) -> Result<Vec<Post>, SophonError> {
+        let params = df_params(rpc, block).await?;
+
+        let mut posts = vec![];
+        for change in changes(config, &self.0, &params) {
+            alert(config, change.clone()).await;
+
+            let tweet = format!("Sophon f3a80c6e TX: {} #darkforest", change);
+            posts.push(Post::new(tweet, block));
+        }
+        self.0 = params;
+
+        Ok(posts)
+    }
+}
+
+/// What changed from `before` to `after`, nothing on the first snapshot.
+/// Elements of one array or struct that changed together are one change.
+fn changes(
+    config: &SophonConfig,
+    before: &BTreeMap<String, String>,
+    after: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut changes = vec![];
+    let mut elements: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+
+    for (name, value) in after {
+        let previous = match before.get(name) {
+            Some(previous) if previous != value => previous,
+            _ => continue,
+        };
+
+        let change = match (name.as_str(), value.as_str()) {
+            ("paused", "true") => "the universe has been paused".to_string(),
+            ("paused", _) => "the universe has resumed".to_string(),
+            ("adminAddress", admin) => format!(
+                "the universe has a new admin, {} has handed over to {}",
+                player_name(config, previous),
+                player_name(config, admin)
+            ),
+            (name, value) => match name.find('[') {
+                Some(index) => {
+                    let element = format!("{} is now {} (was {})", &name[index..], value, previous);
+                    elements.entry(&name[..index]).or_default().push(element);
+                    continue;
+                }
+                None => format!("rule change, {} is now {} (was {})", name, value, previous),
+            },
+        };
+        changes.push(change);
+    }
+
+    for (name, elements) in elements {
+        changes.push(format!("rule change to {}, {}", name, elements.join(", ")));
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(values: &[(&str, &str)]) -> BTreeMap<String, String> {
+        values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn array_changes_are_listed_by_element() {
+        let config = SophonConfig::default();
+        let before = params(&[
+            ("getDefaultStats[2][4]", "250"),
+            ("getDefaultStats[3][4]", "300"),
+            ("getDefaultStats[3][5]", "7"),
+            ("PLANET_RARITY", "16384"),
+        ]);
+        let after = params(&[
+            ("getDefaultStats[2][4]", "300"),
+            ("getDefaultStats[3][4]", "300"),
+            ("getDefaultStats[3][5]", "8"),
+            ("PLANET_RARITY", "16384"),
+        ]);
+
+        assert_eq!(
+            changes(&config, &before, &after),
+            vec!["rule change to getDefaultStats, [2][4] is now 300 (was 250), [3][5] is now 8 (was 7)"]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::config::SophonConfig;
+use crate::graph::{query_all, query_player, Artifact, Hat, Planet};
+use crate::names::player_name;
+use crate::SophonState;
+
+/// seconds a cached profile is trusted before it's fetched again
+const PROFILE_TTL: u64 = 60 * 60 * 6;
+
+/// What Sophon knows about a civilization beyond its address.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlayerProfile {
+    pub id: String,
+    /// location id of the home world
+    pub home_world: Option<String>,
+    /// unix seconds the player joined
+    pub founded: u32,
+    pub planets: usize,
+    /// silver sitting on owned planets
+    pub silver: u64,
+    pub hats: usize,
+    pub artifacts: usize,
+    /// unix seconds this was fetched
+    pub fetched_at: u64,
+}
+
+impl PlayerProfile {
+    /// ie `a civilization founded 3 days ago owning 42 planets`
+    pub fn describe(&self) -> String {
+        let age = now().saturating_sub(self.founded as u64);
+        let founded = match age / (60 * 60 * 24) {
+            0 => format!("{} hours", age / (60 * 60)),
+            1 => "1 day".to_string(),
+            days => format!("{} days", days),
+        };
+
+        let mut description = format!(
+            "a civilization founded {} ago owning {} planets",
+            founded, self.planets
+        );
+        if self.hats > 0 {
+            description.push_str(format!(" and {} hats", self.hats).as_str());
+        }
+
+        description
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default()
+}
+
+/// The player's profile, from cache while it's fresh, otherwise fetched at `block`.
+pub async fn player_profile(
+    state: &mut SophonState,
+    config: &SophonConfig,
+    id: &str,
+    block: u64,
+) -> Option<PlayerProfile> {
+    let now = now();
+
+    if let Some(profile) = state.profiles.get(id) {
+        if now.saturating_sub(profile.fetched_at) < PROFILE_TTL {
+            return Some(profile.clone());
+        }
+    }
+
+    let round = config.round();
+    let max = config.graph_max_results;
+    let player = query_player(round, id, block).await.ok()??;
+    let planets = query_all::<Planet>(round, "owner: $owner", json!({ "owner": id }), block, max)
+        .await
+        .ok()?;
+    let hats = query_all::<Hat>(
+        round,
+        "player: $player",
+        json!({ "player": id }),
+        block,
+        max,
+    )
+    .await
+    .ok()?;
+    let artifacts = query_all::<Artifact>(
+        round,
+        "discoverer: $discoverer",
+        json!({ "discoverer": id }),
+        block,
+        max,
+    )
+    .await
+    .ok()?;
+
+    let profile = PlayerProfile {
+        id: player.id,
+        home_world: player.homeWorld.map(|planet| planet.id),
+        founded: player.initTimestamp,
+        planets: planets.len(),
+        silver: planets
+            .iter()
+            .map(|planet| planet.milliSilverLazy as u64 / 1000)
+            .sum(),
+        hats: hats.len(),
+        artifacts: artifacts.len(),
+        fetched_at: now,
+    };
+
+    // drop anything stale while we're here so the state file doesn't grow forever
+    state
+        .profiles
+        .retain(|_, profile| now.saturating_sub(profile.fetched_at) < PROFILE_TTL);
+    state.profiles.insert(id.to_string(), profile.clone());
+
+    Some(profile)
+}
+
+/// How posts refer to a player, ie `@handle, a civilization founded 3 days ago
+/// owning 42 planets`, or just their name when there's no profile.
+pub async fn describe_player(
+    state: &mut SophonState,
+    config: &SophonConfig,
+    id: &str,
+    block: u64,
+) -> String {
+    let name = player_name(config, id);
+
+    match player_profile(state, config, id, block).await {
+        Some(profile) => format!("{}, {}", name, profile.describe()),
+        None => name,
+    }
+}
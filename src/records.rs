@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::twitter::Post;
+
+const DAY: u64 = 60 * 60 * 24;
+const WEEK: u64 = DAY * 7;
+
+/// A window records are kept over, each one closes on a fixed unix boundary
+/// (midnight utc, and thursday midnight utc for weeks).
+#[derive(Debug, Clone, Copy)]
+pub enum Window {
+    Day,
+    Week,
+}
+
+impl Window {
+    pub const ALL: [Window; 2] = [Window::Day, Window::Week];
+
+    pub fn length(&self) -> u64 {
+        match self {
+            Window::Day => DAY,
+            Window::Week => WEEK,
+        }
+    }
+
+    /// unix seconds the window containing `now` started
+    pub fn start(&self, now: u64) -> u64 {
+        now - now % self.length()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Window::Day => "day",
+            Window::Week => "week",
+        }
+    }
+}
+
+/// The best value of one metric so far in the current window.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Best {
+    /// unix seconds the window started
+    pub window: u64,
+    pub value: u64,
+    /// what set it, ie `the longest voyage`
+    pub label: String,
+    /// the rest of the post, ie `1200 units from Ash Delta to Cobalt Flux`
+    pub text: String,
+    pub block: u64,
+}
+
+/// Records that reset every day and week, so there is always a "biggest move
+/// of the day" to post even once the all-time records are out of reach. The
+/// winner of each window is posted once the window closes.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct RollingRecords {
+    pub day: HashMap<String, Best>,
+    pub week: HashMap<String, Best>,
+}
+
+impl RollingRecords {
+    fn bests(&mut self, window: Window) -> &mut HashMap<String, Best> {
+        match window {
+            Window::Day => &mut self.day,
+            Window::Week => &mut self.week,
+        }
+    }
+
+    /// Keeps `value` as `metric`'s best in every window it beats. Call `close`
+    /// first, a window that has ended is replaced without being posted.
+    pub fn offer(
+        &mut self,
+        metric: &str,
+        value: u64,
+        label: &str,
+        text: String,
+        block: u64,
+        now: u64,
+    ) {
+        for window in Window::ALL.iter() {
+            let start = window.start(now);
+            let bests = self.bests(*window);
+
+            let beaten = match bests.get(metric) {
+                Some(best) => best.window != start || value > best.value,
+                None => true,
+            };
+
+            if beaten && value > 0 {
+                let best = Best {
+                    window: start,
+                    value,
+                    label: label.to_string(),
+                    text: text.clone(),
+                    block,
+                };
+                bests.insert(metric.to_string(), best);
+            }
+        }
+    }
+
+    /// Posts for every window that closed before `now`, forgetting their bests.
+    pub fn close(&mut self, now: u64) -> Vec<Post> {
+        let mut posts = vec![];
+
+        for window in Window::ALL.iter() {
+            let start = window.start(now);
+            let bests = self.bests(*window);
+
+            let mut closed = bests
+                .iter()
+                .filter(|(_, best)| best.window < start)
+                .map(|(metric, _)| metric.clone())
+                .collect::<Vec<_>>();
+            closed.sort();
+
+            for metric in closed {
+                if let Some(best) = bests.remove(&metric) {
+                    let tweet = format!(
+                        "Sophon 1d7a3c55 TX: {} of the {} was {} #darkforest",
+                        best.label,
+                        window.name(),
+                        best.text
+                    );
+                    posts.push(Post::new(tweet, best.block));
+                }
+            }
+        }
+
+        posts
+    }
+}
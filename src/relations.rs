@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::captures::{scan as scan_captures, CaptureState};
+use crate::config::SophonConfig;
+use crate::cycle::{scan_every_cycle, Scan};
+use crate::graph::*;
+use crate::names::player_name;
+use crate::node::*;
+use crate::twitter::Post;
+use crate::{SophonError, SophonShare, SophonState};
+
+/// civilizations ranked for relationship posts
+const TOP_CIVILIZATIONS: usize = 10;
+/// planets at or above this level count towards a civilization's rank
+const RANKED_LEVEL: u32 = 3;
+/// attacks between two civilizations before we call it a war
+const WAR_ATTACKS: u64 = 5;
+
+/// How two players have treated each other.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Relation {
+    /// moves either sent at a planet the other owned
+    pub attacks: u64,
+    /// planets either gave the other
+    pub transfers: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct RelationState {
+    /// last block scanned for PlanetTransferred
+    pub block: Option<u64>,
+    /// newest arrival id counted
+    pub last_arrival_id: u32,
+    /// relations by the two player addresses, sorted and space separated
+    pub relations: HashMap<String, Relation>,
+    /// wars and alliances already posted, by the same kind of key
+    pub posted: HashSet<String>,
+}
+
+/// What relations and captures both read each cycle, fetched once for the two.
+pub struct Moves {
+    /// arrivals in flight at the cycle's block
+    pub arrivals: Vec<Arrival>,
+    /// PlanetTransferred from whichever scan is further behind up to the block
+    pub transfers: Vec<DfEvent>,
+}
+
+/// Builds a graph of who attacks whom, from arrivals between different
+/// owners' planets, and who gives planets to whom, from PlanetTransferred.
+/// Players joined by transfers are clustered into alliances, and wars or
+/// alliances between top civilizations are posted once each. Captures are
+/// scanned in the same cycle, off the same arrivals and transfers.
+pub async fn collect_relations(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
+    scan_every_cycle::<Encounters>(wrapped_state, rpc, config).await
+}
+
+/// Relations and captures, scanned together so they share one set of moves.
+pub struct Encounters {
+    relations: RelationState,
+    captures: CaptureState,
+}
+
+impl Scan for Encounters {
+    fn of(state: &SophonState) -> Self {
+        Encounters {
+            relations: state.relations.clone(),
+            captures: state.captures.clone(),
+        }
+    }
+
+    fn put(self, state: &mut SophonState) {
+        state.relations = self.relations;
+        state.captures = self.captures;
+    }
+
+    async fn scan(
+        &mut self,
+        _state: &SophonState,
+        rpc: &Rpc,
+        config: &SophonConfig,
+        block: u64,
+    ) -> Result<Vec<Post>, SophonError> {
+        let from = [self.relations.block, self.captures.block]
+            .iter()
+            .map(|last| last.map_or(block, |last| last + 1))
+            .min()
+            .unwrap_or(block);
+        let moves = moves(rpc, config, from, block).await?;
+
+        let mut posts = scan(&mut self.relations, config, block, &moves).await?;
+        posts.extend(scan_captures(&mut self.captures, rpc, config, block, &moves).await?);
+        Ok(posts)
+    }
+}
+
+async fn moves(
+    rpc: &Rpc,
+    config: &SophonConfig,
+    from: u64,
+    block: u64,
+) -> Result<Moves, SophonError> {
+    let round = config.round();
+    let in_flight = round.mapping.arrivals_in_flight.as_str();
+
+    Ok(Moves {
+        arrivals: query_all::<Arrival>(
+            round,
+            in_flight,
+            serde_json::json!({}),
+            block,
+            config.graph_max_results,
+        )
+        .await?,
+        transfers: df_events(rpc, "PlanetTransferred", from, block).await?,
+    })
+}
+
+async fn scan(
+    state: &mut RelationState,
+    config: &SophonConfig,
+    block: u64,
+    moves: &Moves,
+) -> Result<Vec<Post>, SophonError> {
+    let round = config.round();
+    let max = config.graph_max_results;
+
+    // with nothing counted yet, these arrivals only mark where counting picks up
+    let counted = state.last_arrival_id;
+    for arrival in &moves.arrivals {
+        let owner = &arrival.toPlanet.owner.id;
+        if counted > 0
+            && arrival.arrivalId > counted
+            && owner != NO_OWNER
+            && *owner != arrival.player.id
+        {
+            relation(state, &arrival.player.id, owner).attacks += 1;
+        }
+        state.last_arrival_id = std::cmp::max(state.last_arrival_id, arrival.arrivalId);
+    }
+
+    let from = match state.block {
+        Some(last) => last + 1,
+        None => block,
+    };
+    for event in moves.transfers.iter().filter(|event| event.block >= from) {
+        let mut loc = None;
+        let mut to = None;
+        for param in &event.params {
+            match param.name.as_str() {
+                "loc" => loc = param.value.clone().into_uint(),
+                "player" => to = param.value.clone().into_address(),
+                _ => {}
+            }
+        }
+        let (loc, to) = match (loc, to) {
+            (Some(loc), Some(to)) => (loc, format!("{:?}", to)),
+            _ => continue,
+        };
+
+        // who gave it away, the owner just before the transfer
+        let id = planet_id(&loc);
+        let planet = query_planet(round, &id, event.block.saturating_sub(1)).await?;
+        if let Some(planet) = planet {
+            if planet.owner.id != NO_OWNER && planet.owner.id != to {
+                relation(state, &planet.owner.id, &to).transfers += 1;
+            }
+        }
+    }
+    state.block = Some(block);
+
+    let ranked = query_all::<Planet>(
+        round,
+        "planetLevel_gte: $level",
+        serde_json::json!({ "level": RANKED_LEVEL }),
+        block,
+        max,
+    )
+    .await?;
+    let ranks = rank(&ranked);
+
+    Ok(notable(state, config, &ranks, block))
+}
+
+fn key(a: &str, b: &str) -> String {
+    if a < b {
+        format!("{} {}", a, b)
+    } else {
+        format!("{} {}", b, a)
+    }
+}
+
+fn relation<'a>(state: &'a mut RelationState, a: &str, b: &str) -> &'a mut Relation {
+    state.relations.entry(key(a, b)).or_default()
+}
+
+/// Top civilizations by planets at or above RANKED_LEVEL, address to rank from 1.
+fn rank(planets: &[Planet]) -> HashMap<String, usize> {
+    let mut owned: HashMap<&str, usize> = HashMap::new();
+    for planet in planets {
+        if planet.owner.id != NO_OWNER {
+            *owned.entry(planet.owner.id.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut owned = owned.into_iter().collect::<Vec<_>>();
+    owned.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    owned
+        .into_iter()
+        .take(TOP_CIVILIZATIONS)
+        .enumerate()
+        .map(|(i, (player, _))| (player.to_string(), i + 1))
+        .collect()
+}
+
+/// Wars and alliances between ranked civilizations we haven't posted yet.
+fn notable(
+    state: &mut RelationState,
+    config: &SophonConfig,
+    ranks: &HashMap<String, usize>,
+    block: u64,
+) -> Vec<Post> {
+    let mut posts = vec![];
+    let ranked = |player: &str| {
+        let rank = ranks[player];
+        format!("{} (#{})", player_name(config, player), rank)
+    };
+
+    let mut wars = state
+        .relations
+        .iter()
+        .filter(|(_, relation)| relation.attacks >= WAR_ATTACKS)
+        .map(|(key, relation)| (key.clone(), relation.attacks))
+        .collect::<Vec<_>>();
+    wars.sort();
+
+    for (key, attacks) in wars {
+        let mut players = key.split(' ');
+        let (a, b) = match (players.next(), players.next()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => continue,
+        };
+        if !ranks.contains_key(a) || !ranks.contains_key(b) {
+            continue;
+        }
+
+        if state.posted.insert(format!("war {}", key)) {
+            let tweet = format!(
+                "Sophon 4d93a0be TX: two top-{} civilizations are at war, {} and {} have traded {} attacks #darkforest",
+                TOP_CIVILIZATIONS,
+                ranked(a),
+                ranked(b),
+                attacks
+            );
+            posts.push(Post::new(tweet, block));
+        }
+    }
+
+    for alliance in alliances(state) {
+        let mut members = alliance
+            .iter()
+            .filter(|player| ranks.contains_key(player.as_str()))
+            .collect::<Vec<_>>();
+        if members.len() < 2 {
+            continue;
+        }
+        members.sort_by_key(|player| ranks[player.as_str()]);
+
+        let key = members
+            .iter()
+            .map(|player| player.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if state.posted.insert(format!("alliance {}", key)) {
+            let named = members
+                .iter()
+                .map(|player| ranked(player))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let tweet = format!(
+                "Sophon 8a61c2f9 TX: an alliance of {} civilizations has formed around {} #darkforest",
+                alliance.len(),
+                named
+            );
+            posts.push(Post::new(tweet, block));
+        }
+    }
+
+    posts
+}
+
+/// Groups of players connected by planet transfers.
+fn alliances(state: &RelationState) -> Vec<Vec<String>> {
+    let mut parent: HashMap<&str, &str> = HashMap::new();
+
+    fn root<'a>(parent: &mut HashMap<&'a str, &'a str>, player: &'a str) -> &'a str {
+        let mut root = player;
+        while let Some(&up) = parent.get(root) {
+            if up == root {
+                break;
+            }
+            root = up;
+        }
+        parent.insert(player, root);
+        root
+    }
+
+    for (key, relation) in &state.relations {
+        if relation.transfers == 0 {
+            continue;
+        }
+        let mut players = key.split(' ');
+        if let (Some(a), Some(b)) = (players.next(), players.next()) {
+            parent.entry(a).or_insert(a);
+            parent.entry(b).or_insert(b);
+            let (a, b) = (root(&mut parent, a), root(&mut parent, b));
+            parent.insert(a, b);
+        }
+    }
+
+    let mut groups: HashMap<&str, Vec<String>> = HashMap::new();
+    let players = parent.keys().copied().collect::<Vec<_>>();
+    for player in players {
+        let root = root(&mut parent, player);
+        groups.entry(root).or_default().push(player.to_string());
+    }
+
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
@@ -0,0 +1,95 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::{sleep, Instant};
+
+use crate::SophonError;
+
+/// first restart delay after a collector fails
+const MIN_BACKOFF: Duration = Duration::from_secs(10);
+/// restart delay doubles up to this
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 30);
+/// a collector that ran this long before failing is considered healthy again
+const HEALTHY_RUN: Duration = Duration::from_secs(60 * 60 * 2);
+/// consecutive failures before we start shouting about it
+const REPORT_THRESHOLD: u32 = 3;
+
+/// Runs `task` as an independent tokio task, restarting it with exponential
+/// backoff whenever it returns, errors or panics. Never returns, so the other
+/// collectors keep running no matter what happens to this one. Dropping it
+/// aborts the task, which is how main stops collectors at the end of a round.
+pub async fn supervise<F, Fut>(name: &'static str, task: F) -> Result<(), SophonError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<(), SophonError>> + Send + 'static,
+{
+    restart(name, task, false).await
+}
+
+/// Like `supervise`, for a task with an end: returns once `task` does without
+/// an error, restarting it as `supervise` would otherwise.
+pub async fn supervise_to_end<F, Fut>(name: &'static str, task: F) -> Result<(), SophonError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<(), SophonError>> + Send + 'static,
+{
+    restart(name, task, true).await
+}
+
+async fn restart<F, Fut>(name: &'static str, task: F, ends: bool) -> Result<(), SophonError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<(), SophonError>> + Send + 'static,
+{
+    let mut backoff = MIN_BACKOFF;
+    let mut failures: u32 = 0;
+
+    loop {
+        let started = Instant::now();
+
+        match Task(tokio::spawn(task())).await {
+            Ok(Ok(())) if ends => return Ok(()),
+            Ok(Ok(())) => eprintln!("sophon: {} exited unexpectedly", name),
+            Ok(Err(err)) => eprintln!("sophon: {} failed: {:?}", name, err),
+            Err(err) if err.is_panic() => eprintln!("sophon: {} panicked", name),
+            Err(err) => eprintln!("sophon: {} was cancelled: {:?}", name, err),
+        }
+
+        // a long healthy run means this is a fresh failure, not a crash loop
+        if started.elapsed() >= HEALTHY_RUN {
+            backoff = MIN_BACKOFF;
+            failures = 0;
+        }
+
+        failures += 1;
+        if failures >= REPORT_THRESHOLD {
+            eprintln!(
+                "sophon: {} has failed {} times in a row, next restart in {:?}",
+                name, failures, backoff
+            );
+        }
+
+        sleep(backoff).await;
+        backoff = std::cmp::min(backoff * 2, MAX_BACKOFF);
+    }
+}
+
+/// A spawned task that is aborted when its handle is dropped, tokio's own
+/// JoinHandle leaves it running.
+struct Task<T>(JoinHandle<T>);
+
+impl<T> Future for Task<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+impl<T> Drop for Task<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
@@ -1,8 +1,60 @@
 use reqwest::multipart;
 use reqwest_oauth1::OAuthClientProvider;
+use serde::{Deserialize, Serialize};
 use tokio_compat_02::FutureExt;
 
+/// most characters twitter takes in one tweet
+const MAX_TWEET: usize = 280;
+/// twitter's error codes for a tweet it will never take, too long and duplicate
+const REJECTED_CODES: &[u32] = &[186, 187];
+
 pub async fn send(tweet: String) -> Result<(), TwitterError> {
+    update_status(tweet, None).await.map(|_| ())
+}
+
+/// Posts `tweets` as a thread, each one replying to the one before. On
+/// failure returns the tweets that didn't go out along with the error.
+pub async fn send_thread(tweets: Vec<String>) -> Result<(), (Vec<String>, TwitterError)> {
+    let mut reply_to = None;
+    let mut tweets = tweets.into_iter();
+    while let Some(tweet) = tweets.next() {
+        match update_status(tweet.clone(), reply_to).await {
+            Ok(id) => reply_to = Some(id),
+            Err(err) => {
+                let unsent = std::iter::once(tweet).chain(tweets).collect();
+                return Err((unsent, err));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `tweet` cut down to fit, keeping a trailing hashtag.
+fn shorten(tweet: &str) -> String {
+    match tweet.rfind(" #") {
+        Some(hashtag) if !tweet[hashtag + 1..].contains(' ') => {
+            fit(&tweet[..hashtag], &tweet[hashtag..])
+        }
+        _ => fit(tweet, ""),
+    }
+}
+
+/// `text` cut short enough that it and `suffix` fit in one tweet.
+fn fit(text: &str, suffix: &str) -> String {
+    let room = MAX_TWEET.saturating_sub(suffix.chars().count());
+    if text.chars().count() <= room {
+        return format!("{}{}", text, suffix);
+    }
+
+    let cut = text
+        .chars()
+        .take(room.saturating_sub(1))
+        .collect::<String>();
+    format!("{}…{}", cut, suffix)
+}
+
+/// Posts one tweet, in reply to `reply_to` if set, and returns its id.
+async fn update_status(tweet: String, reply_to: Option<String>) -> Result<String, TwitterError> {
     let args: Vec<String> = std::env::args().collect();
     let consumer_key = args[1].clone();
     let consumer_secret_key = args[2].clone();
@@ -14,7 +66,12 @@ pub async fn send(tweet: String) -> Result<(), TwitterError> {
 
     let endpoint = "https://api.twitter.com/1.1/statuses/update.json";
 
-    let content = multipart::Form::new().text("status", tweet);
+    let mut content = multipart::Form::new().text("status", shorten(&tweet));
+    if let Some(reply_to) = reply_to {
+        content = content
+            .text("in_reply_to_status_id", reply_to)
+            .text("auto_populate_reply_metadata", "true");
+    }
 
     let response = reqwest::Client::new()
         // enable OAuth1 request
@@ -25,19 +82,89 @@ pub async fn send(tweet: String) -> Result<(), TwitterError> {
         .compat()
         .await?;
 
-    //todo, TwitterApiError here. but duplicate tweets, like if no planet totals have changed, will error, so just print
     if response.status() != 200 {
-        return Err(TwitterError::HttpError(
-            response.text().await.unwrap_or_default(),
-        ));
+        let body = response.text().await.unwrap_or_default();
+
+        // duplicate tweets, like if no planet totals have changed, will never
+        // go out however often they're retried. anything else, a suspended
+        // account or a token without write access, might be fixed
+        return Err(match rejected(&body) {
+            true => TwitterError::Rejected(body),
+            false => TwitterError::HttpError(body),
+        });
+    }
+
+    let status = response.text().await?;
+    let status = serde_json::from_str::<Status>(status.as_str())?;
+    Ok(status.id_str)
+}
+
+#[derive(Deserialize)]
+struct Status {
+    id_str: String,
+}
+
+#[derive(Deserialize)]
+struct ApiErrors {
+    errors: Vec<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    code: u32,
+}
+
+/// Whether an error response says the tweet itself will never be taken.
+fn rejected(body: &str) -> bool {
+    match serde_json::from_str::<ApiErrors>(body) {
+        Ok(response) => response
+            .errors
+            .iter()
+            .any(|error| REJECTED_CODES.contains(&error.code)),
+        Err(_) => false,
+    }
+}
+
+/// A tweet waiting to go out, along with the block its data was read at.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(from = "QueuedPost")]
+pub struct Post {
+    pub text: String,
+    pub block: Option<u64>,
+}
+
+impl Post {
+    pub fn new(text: String, block: u64) -> Self {
+        Post {
+            text,
+            block: Some(block),
+        }
+    }
+}
+
+// older state files queued bare strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QueuedPost {
+    Text(String),
+    Post { text: String, block: Option<u64> },
+}
+
+impl From<QueuedPost> for Post {
+    fn from(queued: QueuedPost) -> Self {
+        match queued {
+            QueuedPost::Text(text) => Post { text, block: None },
+            QueuedPost::Post { text, block } => Post { text, block },
+        }
     }
-    Ok(())
 }
 
 #[derive(Debug)]
 pub enum TwitterError {
     Internal,
     HttpError(String),
+    /// twitter refused the tweet itself, sending it again won't help
+    Rejected(String),
     OAuth,
     TwitterUrl,
 }
@@ -48,6 +175,12 @@ impl From<std::io::Error> for TwitterError {
     }
 }
 
+impl From<serde_json::Error> for TwitterError {
+    fn from(_err: serde_json::Error) -> Self {
+        TwitterError::Internal
+    }
+}
+
 impl From<url::ParseError> for TwitterError {
     fn from(_err: url::ParseError) -> Self {
         TwitterError::TwitterUrl
@@ -65,3 +198,41 @@ impl From<reqwest_oauth1::Error> for TwitterError {
         TwitterError::OAuth
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_keeps_the_suffix() {
+        assert_eq!(fit("short", " #darkforest"), "short #darkforest");
+
+        let long = "x".repeat(400);
+        let tweet = fit(&long, " #darkforest");
+        assert_eq!(tweet.chars().count(), MAX_TWEET);
+        assert!(tweet.ends_with("x… #darkforest"));
+    }
+
+    #[test]
+    fn shorten_keeps_the_hashtag() {
+        let long = format!("Sophon TX: {} #darkforest", "x".repeat(400));
+        let tweet = shorten(&long);
+        assert_eq!(tweet.chars().count(), MAX_TWEET);
+        assert!(tweet.ends_with("x… #darkforest"));
+
+        assert_eq!(shorten("short #darkforest"), "short #darkforest");
+    }
+
+    #[test]
+    fn only_duplicates_and_long_tweets_are_rejected() {
+        let error = |code: u32| format!(r#"{{"errors":[{{"code":{},"message":""}}]}}"#, code);
+
+        assert!(rejected(&error(187)));
+        assert!(rejected(&error(186)));
+        // suspended, read-only app, bad token
+        assert!(!rejected(&error(64)));
+        assert!(!rejected(&error(261)));
+        assert!(!rejected(&error(89)));
+        assert!(!rejected("<html>bad gateway</html>"));
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::config::SophonConfig;
+use crate::cycle::{scan_every_cycle, Scan};
+use crate::graph::{planet_id, query_planet};
+use crate::names::{planet_name, player_name};
+use crate::node::*;
+use crate::twitter::Post;
+use crate::{SophonError, SophonShare, SophonState};
+
+/// upgrade branches in contract order
+const BRANCHES: [&str; 3] = ["defense", "range", "speed"];
+/// branch stats are posted every this many upgrades
+const REPORT_EVERY: u64 = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct UpgradeState {
+    /// last block scanned for PlanetUpgraded
+    pub block: Option<u64>,
+    /// ranks in each branch of every planet seen upgrading
+    pub ranks: HashMap<String, [u64; 3]>,
+    /// branches that have had a planet fully upgraded
+    pub maxed: HashSet<usize>,
+    /// upgrades bought in each branch
+    pub upgrades: [u64; 3],
+    /// upgrade count branch stats were last posted at
+    pub reported: u64,
+}
+
+/// Follows PlanetUpgraded, posting the first planet to max out each branch
+/// and, every REPORT_EVERY upgrades, which branches the universe favors.
+pub async fn collect_upgrades(
+    wrapped_state: Arc<Mutex<SophonShare>>,
+    rpc: Arc<Rpc>,
+    config: Arc<SophonConfig>,
+) -> Result<(), SophonError> {
+    scan_every_cycle::<UpgradeState>(wrapped_state, rpc, config).await
+}
+
+impl Scan for UpgradeState {
+    fn of(state: &SophonState) -> Self {
+        state.upgrades.clone()
+    }
+
+    fn put(self, state: &mut SophonState) {
+        state.upgrades = self;
+    }
+
+    async fn scan(
+        &mut self,
+        _state: &SophonState,
+        rpc: &Rpc,
+        config: &SophonConfig,
+        block: u64,
+    ) -> Result<Vec<Post>, SophonError> {
+        scan(self, rpc, config, block).await
+    }
+}
+
+async fn scan(
+    state: &mut UpgradeState,
+    rpc: &Rpc,
+    config: &SophonConfig,
+    block: u64,
+) -> Result<Vec<Post>, SophonError> {
+    // the first cycle only counts what happens from now on
+    let from = match state.block {
+        Some(last) => last + 1,
+        None => block,
+    };
+
+    let events = df_events(rpc, "PlanetUpgraded", from, block).await?;
+    let max_ranks = if events.is_empty() {
+        vec![]
+    } else {
+        df_upgrade_ranks(rpc, block).await?
+    };
+
+    let mut posts = vec![];
+    for event in events {
+        let loc = event
+            .params
+            .iter()
+            .find(|param| param.name == "loc")
+            .and_then(|param| param.value.clone().into_uint());
+        let loc = match loc {
+            Some(loc) => loc,
+            None => continue,
+        };
+
+        let id = planet_id(&loc);
+        // a planet we haven't seen upgrade before had its earlier ranks already
+        let before = match state.ranks.get(&id) {
+            Some(before) => *before,
+            None => {
+                df_planet_info(rpc, loc, event.block.saturating_sub(1))
+                    .await?
+                    .upgrade_state
+            }
+        };
+        let ranks = df_planet_info(rpc, loc, block).await?.upgrade_state;
+        state.ranks.insert(id.clone(), ranks);
+
+        for (branch, (rank, before)) in ranks.iter().zip(before.iter()).enumerate() {
+            state.upgrades[branch] += rank.saturating_sub(*before);
+
+            let maxed = max_ranks.get(branch).map(|max| *rank >= *max as u64);
+            if maxed == Some(true) && !state.maxed.contains(&branch) {
+                // without the planet there's nobody to credit, so the next one gets the post
+                let planet = match query_planet(config.round(), &id, block).await? {
+                    Some(planet) => planet,
+                    None => continue,
+                };
+
+                let tweet = format!(
+                    "Sophon 9e4b2d17 TX: first planet fully upgraded in {}: {}, a {} held by {} #darkforest",
+                    BRANCHES[branch],
+                    planet_name(config, &id),
+                    planet.describe(),
+                    player_name(config, &planet.owner.id),
+                );
+                posts.push(Post::new(tweet, event.block));
+                state.maxed.insert(branch);
+            }
+        }
+    }
+    state.block = Some(block);
+
+    let total = state.upgrades.iter().sum::<u64>();
+    let milestone = (total / REPORT_EVERY) * REPORT_EVERY;
+    if milestone > state.reported {
+        let favored = (0..BRANCHES.len())
+            .max_by_key(|branch| state.upgrades[*branch])
+            .unwrap_or_default();
+        let shares = BRANCHES
+            .iter()
+            .zip(state.upgrades.iter())
+            .map(|(name, count)| format!("{} {}%", name, count * 100 / total))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let tweet = format!(
+            "Sophon 27c5f0a3 TX: {} planet upgrades and the universe favors {}: {} #darkforest",
+            milestone, BRANCHES[favored], shares
+        );
+        posts.push(Post::new(tweet, block));
+
+        state.reported = milestone;
+    }
+
+    Ok(posts)
+}
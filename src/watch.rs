@@ -0,0 +1,87 @@
+use crate::alert::alert;
+use crate::config::SophonConfig;
+use crate::graph::Arrival;
+use crate::names::{planet_name, player_name};
+use crate::profiles::now;
+use crate::twitter::send;
+use crate::SophonState;
+
+/// Warns about arrivals in flight carrying at least `watch.min_energy` to a
+/// watched planet, or to any planet of a watched player, once per arrival.
+/// Players moving energy between their own planets aren't a threat and are
+/// skipped. Returns whether `state` changed.
+pub async fn watch_arrivals(
+    state: &mut SophonState,
+    config: &SophonConfig,
+    arrivals: &[Arrival],
+) -> bool {
+    let watch = &config.watch;
+    if watch.targets.is_empty() {
+        return false;
+    }
+
+    let watched = |id: &str| {
+        let id = id.trim_start_matches("0x").to_lowercase();
+        watch
+            .targets
+            .iter()
+            .any(|target| target.trim_start_matches("0x").to_lowercase() == id)
+    };
+
+    let mut dirty = false;
+
+    // forget arrivals that have landed
+    let before = state.warned_arrivals.len();
+    state
+        .warned_arrivals
+        .retain(|id| arrivals.iter().any(|arrival| &arrival.id == id));
+    dirty |= state.warned_arrivals.len() != before;
+
+    for arrival in arrivals {
+        let target = &arrival.toPlanet;
+        let energy = arrival.milliEnergyArriving / 1000;
+
+        if energy < watch.min_energy
+            || arrival.player.id == target.owner.id
+            || !(watched(&target.id) || watched(&target.owner.id))
+            || state.warned_arrivals.contains(&arrival.id)
+        {
+            continue;
+        }
+
+        let message = format!(
+            "Sophon 7c4e19d3 TX: {} energy inbound to {}, a {} held by {}, from {}, arriving in {} #darkforest",
+            energy,
+            planet_name(config, &target.id),
+            target.describe(),
+            player_name(config, &target.owner.id),
+            player_name(config, &arrival.player.id),
+            countdown(arrival.arrivalTime as u64),
+        );
+
+        // straight out rather than through the queue, a countdown is no use hours late
+        if watch.post {
+            if let Err(err) = send(message.clone()).await {
+                eprintln!("sophon: arrival warning failed: {:?}", err);
+                alert(config, message).await;
+            }
+        } else {
+            alert(config, message).await;
+        }
+
+        state.warned_arrivals.insert(arrival.id.clone());
+        dirty = true;
+    }
+
+    dirty
+}
+
+/// time left until unix seconds `at`, ie `1h 23m`
+fn countdown(at: u64) -> String {
+    let left = at.saturating_sub(now());
+    match (left / (60 * 60), (left / 60) % 60) {
+        (0, 0) => "under a minute".to_string(),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use crate::graph::Arrival;
+
+/// players listed alongside a whale alert
+pub const TOP_MOVERS: usize = 3;
+
+/// Everything one player has in flight right now.
+#[derive(Debug, Clone, Default)]
+pub struct Motion {
+    pub player: String,
+    pub milli_silver: u64,
+    pub milli_energy: u64,
+    pub moves: usize,
+}
+
+/// Totals of `arrivals` per player, most silver first, so a player splitting
+/// silver over many moves still shows up as the whale they are.
+pub fn in_motion(arrivals: &[Arrival]) -> Vec<Motion> {
+    let mut players: HashMap<&str, Motion> = HashMap::new();
+
+    for arrival in arrivals {
+        let motion = players
+            .entry(arrival.player.id.as_str())
+            .or_insert_with(|| Motion {
+                player: arrival.player.id.clone(),
+                ..Motion::default()
+            });
+        motion.milli_silver += arrival.milliSilverMoved as u64;
+        motion.milli_energy += arrival.milliEnergyArriving as u64;
+        motion.moves += 1;
+    }
+
+    let mut motions = players.into_values().collect::<Vec<_>>();
+    motions.sort_by(|a, b| {
+        (b.milli_silver, b.milli_energy, &a.player).cmp(&(
+            a.milli_silver,
+            a.milli_energy,
+            &b.player,
+        ))
+    });
+    motions
+}